@@ -6,8 +6,12 @@ use gtk::{gio,
           subclass::prelude::*,
           Application};
 use once_cell::unsync::OnceCell;
+use utopia_common::library::preferences::DiagType;
 
-use crate::{config, uev::handle_event, utopia::UtopiaWindow};
+use crate::{config,
+            uev::{handle_event, UtopiaMessage, UtopiaRequest},
+            uri::UtopiaUri,
+            utopia::UtopiaWindow};
 
 mod imp {
 	use super::*;
@@ -15,7 +19,12 @@ mod imp {
 	#[derive(Default, Debug)]
 	pub struct UtopiaFrontend {
 		pub window: OnceCell<WeakRef<UtopiaWindow>>,
-		pub utopia: Rc<std::cell::RefCell<Option<crate::uev::UtopiaEvents>>>
+		pub utopia: Rc<std::cell::RefCell<Option<crate::uev::UtopiaEvents>>>,
+		pub dbus: Rc<crate::dbus::UtopiaDBus>,
+		pub pending_uris: Rc<std::cell::RefCell<Vec<UtopiaUri>>>,
+		pub library_loaded: std::cell::Cell<bool>,
+		pub settings: OnceCell<gio::Settings>,
+		pub notifier: Rc<crate::notifications::UtopiaNotifier>
 	}
 
 	#[glib::object_subclass]
@@ -34,12 +43,21 @@ mod imp {
 			window.present();
 		}
 
+		fn shutdown(&self, application: &Self::Type) {
+			crate::persist::flush();
+			self.parent_shutdown(application);
+		}
+
 		fn startup(&self, application: &Self::Type) {
 			self.parent_startup(application);
 			application.set_resource_base_path(Some("/dev/sp1rit/Utopia/"));
+			self.settings
+				.set(gio::Settings::new(config::APP_ID))
+				.expect("Failed to init application settings");
 
 			let (uev, tx, rx) = crate::uev::UtopiaEvents::new();
 			self.utopia.replace(Some(uev));
+			self.dbus.set_sender(tx.clone());
 
 			let window = UtopiaWindow::new(application);
 			window.set_title(Some("Utopia"));
@@ -48,9 +66,19 @@ mod imp {
 				.expect("Failed to init application window");
 
 			let txw = tx.clone();
-			rx.attach(None, move |msg| {
-				handle_event(msg, tx.clone(), window.downgrade().clone().upgrade().unwrap())
-			});
+			let dbus = self.dbus.clone();
+			let notifier = self.notifier.clone();
+			rx.attach(None, glib::clone!(@weak application => @default-return glib::Continue(false), move |msg| {
+				dbus.observe(&msg);
+				notifier.observe(application.upcast_ref(), &application.settings(), &msg);
+				let refreshed = matches!(msg, UtopiaMessage::RefreshGameLibrary(_));
+				let cont = handle_event(msg, tx.clone(), window.downgrade().clone().upgrade().unwrap());
+				if refreshed {
+					imp::UtopiaFrontend::from_instance(&application).library_loaded.set(true);
+					application.handle_pending_uris();
+				}
+				cont
+			}));
 			application.get_main_window().init_listener(txw);
 			self.utopia.borrow().as_ref().unwrap().start();
 			self.utopia.borrow_mut().as_mut().unwrap().request_library();
@@ -59,6 +87,33 @@ mod imp {
 			application.setup_actions();
 			application.setup_accels();
 		}
+
+		fn open(&self, application: &Self::Type, files: &[gio::File], _hint: &str) {
+			application.activate();
+			for file in files {
+				let uri = file.uri();
+				match UtopiaUri::parse(&uri) {
+					Some(uri) => self.pending_uris.borrow_mut().push(uri),
+					None => eprintln!("Ignoring unsupported link {}", uri)
+				}
+			}
+			application.handle_pending_uris();
+		}
+
+		fn dbus_register(
+			&self,
+			application: &Self::Type,
+			connection: &gio::DBusConnection,
+			object_path: &str
+		) -> Result<(), glib::Error> {
+			self.parent_dbus_register(application, connection, object_path)?;
+			self.dbus.register(connection, object_path)
+		}
+
+		fn dbus_unregister(&self, application: &Self::Type, connection: &gio::DBusConnection, object_path: &str) {
+			self.dbus.unregister(connection);
+			self.parent_dbus_unregister(application, connection, object_path);
+		}
 	}
 
 	impl GtkApplicationImpl for UtopiaFrontend {}
@@ -74,7 +129,7 @@ impl UtopiaFrontend {
 	pub fn new() -> Self {
 		glib::Object::new(&[
 			("application-id", &config::APP_ID.to_owned()),
-			("flags", &gio::ApplicationFlags::empty())
+			("flags", &gio::ApplicationFlags::HANDLES_OPEN)
 		])
 		.unwrap()
 	}
@@ -84,6 +139,70 @@ impl UtopiaFrontend {
 		imp.window.get().unwrap().clone().upgrade().unwrap()
 	}
 
+	pub fn settings(&self) -> gio::Settings {
+		let imp = imp::UtopiaFrontend::from_instance(self);
+		imp.settings.get().expect("Settings used before startup").clone()
+	}
+
+	/// Handles links whose items are known by now, the rest is kept
+	/// until the library got loaded.
+	fn handle_pending_uris(&self) {
+		let imp = imp::UtopiaFrontend::from_instance(self);
+		let window = self.get_main_window();
+		let pending = imp.pending_uris.take();
+		for uri in pending {
+			let handled = match &uri {
+				UtopiaUri::Game(uuid) => window.show_item(uuid),
+				UtopiaUri::Launch(uuid) => match window.item_name(uuid) {
+					Some(name) => {
+						window.show_item(uuid);
+						self.confirm_launch(uuid, &name);
+						true
+					},
+					None => false
+				}
+			};
+			if !handled {
+				if imp.library_loaded.get() {
+					eprintln!("Ignoring link to unknown game {}", uri.uuid());
+				} else {
+					imp.pending_uris.borrow_mut().push(uri);
+				}
+			}
+		}
+	}
+
+	fn confirm_launch(&self, uuid: &str, name: &str) {
+		let imp = imp::UtopiaFrontend::from_instance(self);
+		let win = self.get_main_window();
+
+		let diag = gtk::MessageDialog::new(
+			Some(&win),
+			gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+			gtk::MessageType::Question,
+			gtk::ButtonsType::None,
+			&format!("Launch {}?", name)
+		);
+		diag.set_secondary_text(Some("A link requested to launch this game."));
+		diag.add_button("_Cancel", gtk::ResponseType::Cancel);
+		diag.add_button("_Launch", gtk::ResponseType::Accept);
+		diag.set_default_response(gtk::ResponseType::Accept);
+
+		let uuid = uuid.to_owned();
+		let utopia = imp.utopia.clone();
+		diag.connect_response(move |diag, response| {
+			if response == gtk::ResponseType::Accept {
+				if let Some(uev) = utopia.borrow().as_ref() {
+					if let Err(e) = uev.channel.clone().try_send(UtopiaRequest::TriggerLaunch(uuid.clone())) {
+						eprintln!("Error requesting {} to launch: {}", uuid, e);
+					}
+				}
+			}
+			diag.close();
+		});
+		diag.show();
+	}
+
 	fn setup_actions(&self) {
 		let quit = gio::SimpleAction::new("quit", None);
 		quit.connect_activate(glib::clone!(@weak self as app => move |_, _| {
@@ -96,6 +215,70 @@ impl UtopiaFrontend {
 			app.show_about_diag()
 		}));
 		self.add_action(&about);
+
+		let launch = gio::SimpleAction::new("launch", Some(glib::VariantTy::STRING));
+		launch.connect_activate(glib::clone!(@weak self as app => move |_, uuid| {
+			if let Some(uuid) = uuid.and_then(|uuid| uuid.get::<String>()) {
+				let imp = imp::UtopiaFrontend::from_instance(&app);
+				if let Some(uev) = imp.utopia.borrow().as_ref() {
+					if let Err(e) = uev.channel.clone().try_send(UtopiaRequest::TriggerLaunch(uuid.clone())) {
+						eprintln!("Error requesting {} to launch: {}", uuid, e);
+					}
+				}
+			}
+		}));
+		self.add_action(&launch);
+
+		let show_game = gio::SimpleAction::new("show-game", Some(glib::VariantTy::STRING));
+		show_game.connect_activate(glib::clone!(@weak self as app => move |_, uuid| {
+			if let Some(uuid) = uuid.and_then(|uuid| uuid.get::<String>()) {
+				let imp = imp::UtopiaFrontend::from_instance(&app);
+				imp.pending_uris.borrow_mut().push(UtopiaUri::Game(uuid));
+				app.activate();
+				app.handle_pending_uris();
+			}
+		}));
+		self.add_action(&show_game);
+
+		let preferences = gio::SimpleAction::new("preferences", None);
+		preferences.connect_activate(glib::clone!(@weak self as app => move |_, _| {
+			// the scope alone addresses the core
+			app.request_preferences(String::new(), DiagType::Core);
+		}));
+		self.add_action(&preferences);
+
+		let integration_preferences = gio::SimpleAction::new("integration-preferences", Some(glib::VariantTy::STRING));
+		integration_preferences.connect_activate(glib::clone!(@weak self as app => move |_, uuid| {
+			if let Some(uuid) = uuid.and_then(|uuid| uuid.get::<String>()) {
+				app.request_preferences(uuid, DiagType::Provider);
+			}
+		}));
+		self.add_action(&integration_preferences);
+
+		let import = gio::SimpleAction::new("import-artwork", None);
+		import.connect_activate(glib::clone!(@weak self as app => move |_, _| {
+			let win = app.get_main_window();
+			crate::import::run(win.upcast_ref(), win.items());
+		}));
+		self.add_action(&import);
+
+		let settings = self.settings();
+		for key in &["notify-update-finished", "notify-updatable", "notify-unexpected-exit", "sort-order"] {
+			self.add_action(&settings.create_action(key));
+		}
+	}
+
+	fn request_preferences(&self, provider: String, scope: DiagType) {
+		let imp = imp::UtopiaFrontend::from_instance(self);
+		if let Some(uev) = imp.utopia.borrow().as_ref() {
+			if let Err(e) = uev
+				.channel
+				.clone()
+				.try_send(UtopiaRequest::TriggerPreferenceDiag(provider.clone(), scope))
+			{
+				eprintln!("Error requesting preferences of {}: {}", provider, e);
+			}
+		}
 	}
 
 	fn setup_accels(&self) {
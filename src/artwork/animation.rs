@@ -0,0 +1,168 @@
+use std::{io::Cursor, time::Duration};
+
+use gtk::{gdk, gdk::subclass::prelude::*, glib, prelude::*, subclass::prelude::*};
+use image::{codecs::{gif::GifDecoder, png::PngDecoder},
+            imageops::FilterType,
+            AnimationDecoder,
+            ImageFormat,
+            RgbaImage};
+
+/// Used for frames that don't specify a delay, like most browsers do.
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+const MIN_DELAY: Duration = Duration::from_millis(20);
+/// Longer animations are shown as still image.
+const MAX_FRAMES: usize = 240;
+/// Upper bound for all frames at full size, so that huge animations
+/// are shown as still image instead of taking ages to decode.
+const MAX_BYTES: usize = 128 * 1024 * 1024;
+
+pub type DecodedFrames = Vec<(RgbaImage, Duration)>;
+/// Frames of an animation ready to be shown.
+pub type Frames = Vec<(gdk::Texture, Duration)>;
+
+/// Decodes animated GIF and APNG images into frames scaled to the
+/// given size. Returns `None` for still images, other formats and
+/// animations beyond `MAX_FRAMES` or `MAX_BYTES`.
+pub fn decode_frames(bytes: &[u8], width: u32, height: u32) -> Result<Option<DecodedFrames>, String> {
+	let frames: image::Frames = match image::guess_format(bytes) {
+		Ok(ImageFormat::Gif) => GifDecoder::new(Cursor::new(bytes))
+			.map_err(|e| e.to_string())?
+			.into_frames(),
+		Ok(ImageFormat::Png) => {
+			let decoder = PngDecoder::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+			if !decoder.is_apng() {
+				return Ok(None);
+			}
+			decoder.apng().into_frames()
+		},
+		_ => return Ok(None)
+	};
+	// scaled one by one, only a single frame is around at full size
+	let mut decoded = Vec::new();
+	let mut bytes = 0;
+	for frame in frames {
+		let frame = frame.map_err(|e| e.to_string())?;
+		bytes += frame.buffer().as_raw().len();
+		if decoded.len() == MAX_FRAMES || bytes > MAX_BYTES {
+			return Ok(None);
+		}
+		let (numer, denom) = frame.delay().numer_denom_ms();
+		let delay = match denom {
+			0 => DEFAULT_DELAY,
+			denom => Duration::from_millis((numer / denom) as u64)
+		};
+		let delay = if delay.is_zero() { DEFAULT_DELAY } else { delay.max(MIN_DELAY) };
+		let image = image::imageops::resize(frame.buffer(), width, height, FilterType::Lanczos3);
+		decoded.push((image, delay));
+	}
+	if decoded.len() < 2 {
+		return Ok(None);
+	}
+	Ok(Some(decoded))
+}
+
+mod imp {
+	use std::cell::{Cell, RefCell};
+
+	use super::*;
+
+	#[derive(Debug, Default)]
+	pub struct AnimatedTexture {
+		pub frames: RefCell<Frames>,
+		pub current: Cell<usize>,
+		pub scale: Cell<i32>,
+		pub timeout: RefCell<Option<glib::SourceId>>
+	}
+
+	#[glib::object_subclass]
+	impl ObjectSubclass for AnimatedTexture {
+		type Interfaces = (gdk::Paintable,);
+		type ParentType = glib::Object;
+		type Type = super::AnimatedTexture;
+
+		const NAME: &'static str = "UtopiaAnimatedTexture";
+	}
+
+	impl ObjectImpl for AnimatedTexture {
+		fn dispose(&self, obj: &Self::Type) {
+			obj.pause();
+		}
+	}
+
+	impl PaintableImpl for AnimatedTexture {
+		fn intrinsic_width(&self, _paintable: &Self::Type) -> i32 {
+			self.frames
+				.borrow()
+				.first()
+				.map_or(0, |(texture, _)| texture.width() / self.scale.get().max(1))
+		}
+
+		fn intrinsic_height(&self, _paintable: &Self::Type) -> i32 {
+			self.frames
+				.borrow()
+				.first()
+				.map_or(0, |(texture, _)| texture.height() / self.scale.get().max(1))
+		}
+
+		fn snapshot(&self, _paintable: &Self::Type, snapshot: &gdk::Snapshot, width: f64, height: f64) {
+			if let Some((texture, _)) = self.frames.borrow().get(self.current.get()) {
+				texture.snapshot(snapshot, width, height);
+			}
+		}
+	}
+}
+
+glib::wrapper! {
+	/// Animated cover art, rendered for a given scale factor. Starts
+	/// paused on the first frame.
+	pub struct AnimatedTexture(ObjectSubclass<imp::AnimatedTexture>)
+		@implements gdk::Paintable;
+}
+
+impl AnimatedTexture {
+	pub fn new(frames: Frames, scale: i32) -> Self {
+		let paintable: Self = glib::Object::new(&[]).expect("Failed to create AnimatedTexture");
+		let self_ = imp::AnimatedTexture::from_instance(&paintable);
+		self_.frames.replace(frames);
+		self_.scale.set(scale);
+		paintable
+	}
+
+	pub fn is_playing(&self) -> bool {
+		let self_ = imp::AnimatedTexture::from_instance(self);
+		self_.timeout.borrow().is_some()
+	}
+
+	pub fn play(&self) {
+		if !self.is_playing() {
+			self.schedule_next();
+		}
+	}
+
+	pub fn pause(&self) {
+		let self_ = imp::AnimatedTexture::from_instance(self);
+		if let Some(timeout) = self_.timeout.take() {
+			glib::source_remove(timeout);
+		}
+	}
+
+	fn schedule_next(&self) {
+		let self_ = imp::AnimatedTexture::from_instance(self);
+		let delay = match self_.frames.borrow().get(self_.current.get()) {
+			Some((_, delay)) => *delay,
+			None => return
+		};
+		let timeout = glib::timeout_add_local_once(
+			delay,
+			glib::clone!(@weak self as animation => move || {
+				let self_ = imp::AnimatedTexture::from_instance(&animation);
+				self_.timeout.replace(None);
+				let frames = self_.frames.borrow().len();
+				self_.current.set((self_.current.get() + 1) % frames);
+				animation.invalidate_contents();
+				animation.schedule_next();
+			})
+		);
+		self_.timeout.replace(Some(timeout));
+	}
+}
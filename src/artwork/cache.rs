@@ -0,0 +1,275 @@
+use std::{cell::RefCell,
+          collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+          hash::{Hash, Hasher},
+          path::PathBuf};
+
+use gtk::{gdk, glib, prelude::*};
+use utopia_common::library::artwork::ArtworkData;
+
+use super::{animation::Frames, Scaling};
+
+/// Upper bound for decoded textures kept in memory.
+const MAX_BYTES: usize = 96 * 1024 * 1024;
+/// Upper bound for decoded animations kept in memory, the most recent
+/// one is kept regardless.
+const MAX_ANIMATION_BYTES: usize = 64 * 1024 * 1024;
+/// Upper bound for the thumbnails on disk.
+const MAX_THUMBNAIL_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+	pub uuid: String,
+	pub width: i32,
+	pub height: i32,
+	scaling: Scaling,
+	fingerprint: u64
+}
+
+impl CacheKey {
+	/// Key of an artwork with the given [`fingerprint`].
+	pub fn new(uuid: &str, fingerprint: u64, width: i32, height: i32, scaling: Scaling) -> Self {
+		Self {
+			uuid: uuid.to_owned(),
+			width,
+			height,
+			scaling,
+			fingerprint
+		}
+	}
+
+	/// Key of a generated placeholder, which depends on the title
+	/// instead of any artwork.
+	pub fn placeholder(uuid: &str, name: &str, width: i32, height: i32) -> Self {
+		let mut hasher = DefaultHasher::new();
+		("placeholder", name).hash(&mut hasher);
+		Self {
+			uuid: uuid.to_owned(),
+			width,
+			height,
+			scaling: Scaling::Exact,
+			fingerprint: hasher.finish()
+		}
+	}
+
+	/// Key of a generated provider monogram.
+	pub fn monogram(uuid: &str, name: &str, size: i32) -> Self {
+		let mut hasher = DefaultHasher::new();
+		("monogram", name).hash(&mut hasher);
+		Self {
+			uuid: uuid.to_owned(),
+			width: size,
+			height: size,
+			scaling: Scaling::Exact,
+			fingerprint: hasher.finish()
+		}
+	}
+
+	/// Location of the on-disk thumbnail for this key.
+	pub fn thumbnail(&self) -> PathBuf {
+		let uuid = super::file_name(&self.uuid);
+		let scaling = match self.scaling {
+			Scaling::Exact => "",
+			Scaling::Fit => "-fit"
+		};
+		thumbnail_dir().join(format!(
+			"{}-{}x{}{}-{:016x}.png",
+			uuid, self.width, self.height, scaling, self.fingerprint
+		))
+	}
+}
+
+pub fn thumbnail_dir() -> PathBuf {
+	glib::user_cache_dir().join("gtopia").join("thumbnails")
+}
+
+/// Deletes the oldest thumbnails until the rest fit into
+/// `MAX_THUMBNAIL_BYTES`, along with leftovers of interrupted writes.
+pub fn prune_thumbnails() {
+	let entries = match std::fs::read_dir(thumbnail_dir()) {
+		Ok(entries) => entries,
+		Err(_) => return
+	};
+	let mut thumbnails = Vec::new();
+	for entry in entries.filter_map(Result::ok) {
+		let path = entry.path();
+		let meta = match entry.metadata() {
+			Ok(meta) => meta,
+			Err(_) => continue
+		};
+		if path.extension().map_or(false, |extension| extension == "part") {
+			let _ = std::fs::remove_file(&path);
+			continue;
+		}
+		let modified = meta.modified().unwrap_or(std::time::UNIX_EPOCH);
+		thumbnails.push((modified, meta.len(), path));
+	}
+	// newest first, everything past the cap goes
+	thumbnails.sort_by(|a, b| b.0.cmp(&a.0));
+	let mut total = 0;
+	for (_, len, path) in thumbnails {
+		total += len;
+		if total > MAX_THUMBNAIL_BYTES {
+			if let Err(e) = std::fs::remove_file(&path) {
+				eprintln!("Failed removing thumbnail {}: {}", path.display(), e);
+			}
+		}
+	}
+}
+
+/// Identity of the artwork source, so that changed artwork does not hit
+/// stale thumbnails. Reads the whole artwork, so this belongs on the
+/// workers; see [`known_fingerprint`].
+pub fn fingerprint(data: &ArtworkData) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	match data {
+		ArtworkData::Data(data, has_alpha, bits_per_sample, width, height, rowstride) => {
+			(has_alpha, bits_per_sample, width, height, rowstride).hash(&mut hasher);
+			data.hash(&mut hasher);
+		},
+		ArtworkData::Uri(uri) => uri.hash(&mut hasher),
+		ArtworkData::Path(path) => {
+			path.hash(&mut hasher);
+			if let Ok(modified) = std::fs::metadata(path).and_then(|meta| meta.modified()) {
+				modified.hash(&mut hasher);
+			}
+		}
+	}
+	hasher.finish()
+}
+
+/// Tells the artworks of an item apart without looking at their
+/// content.
+fn source_id(data: &ArtworkData) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	match data {
+		ArtworkData::Data(data, has_alpha, bits_per_sample, width, height, rowstride) => {
+			(0, has_alpha, bits_per_sample, width, height, rowstride, data.len()).hash(&mut hasher)
+		},
+		ArtworkData::Uri(uri) => (1, uri).hash(&mut hasher),
+		ArtworkData::Path(path) => (2, path).hash(&mut hasher)
+	}
+	hasher.finish()
+}
+
+/// The fingerprint of the artwork, if it was computed since the
+/// artwork of the item last changed.
+pub fn known_fingerprint(uuid: &str, data: &ArtworkData) -> Option<u64> {
+	FINGERPRINTS.with(|fingerprints| {
+		fingerprints
+			.borrow()
+			.get(uuid)
+			.and_then(|known| known.get(&source_id(data)).copied())
+	})
+}
+
+pub fn remember_fingerprint(uuid: &str, data: &ArtworkData, fingerprint: u64) {
+	FINGERPRINTS.with(|fingerprints| {
+		fingerprints
+			.borrow_mut()
+			.entry(uuid.to_owned())
+			.or_default()
+			.insert(source_id(data), fingerprint);
+	})
+}
+
+pub fn forget_fingerprints(uuid: &str) {
+	FINGERPRINTS.with(|fingerprints| fingerprints.borrow_mut().remove(uuid));
+}
+
+#[derive(Default)]
+struct TextureCache {
+	entries: HashMap<CacheKey, (gdk::Texture, u64)>,
+	bytes: usize,
+	tick: u64
+}
+
+impl TextureCache {
+	fn size_of(texture: &gdk::Texture) -> usize {
+		texture.width() as usize * texture.height() as usize * 4
+	}
+
+	fn get(&mut self, key: &CacheKey) -> Option<gdk::Texture> {
+		self.tick += 1;
+		let tick = self.tick;
+		self.entries.get_mut(key).map(|(texture, used)| {
+			*used = tick;
+			texture.clone()
+		})
+	}
+
+	fn insert(&mut self, key: CacheKey, texture: gdk::Texture) {
+		self.tick += 1;
+		self.bytes += Self::size_of(&texture);
+		if let Some((old, _)) = self.entries.insert(key, (texture, self.tick)) {
+			self.bytes -= Self::size_of(&old);
+		}
+		while self.bytes > MAX_BYTES && self.entries.len() > 1 {
+			let lru = self
+				.entries
+				.iter()
+				.min_by_key(|(_, (_, used))| *used)
+				.map(|(key, _)| key.clone())
+				.unwrap();
+			if let Some((old, _)) = self.entries.remove(&lru) {
+				self.bytes -= Self::size_of(&old);
+			}
+		}
+	}
+}
+
+/// Decoded animations, `None` for covers that turned out to be still.
+/// Only the most recent animations are kept, stills are remembered so
+/// they are not fetched again.
+#[derive(Default)]
+struct AnimationCache {
+	entries: HashMap<CacheKey, Option<Frames>>,
+	/// animated entries, oldest first
+	animated: VecDeque<CacheKey>,
+	bytes: usize
+}
+
+impl AnimationCache {
+	fn size_of(frames: &Option<Frames>) -> usize {
+		frames.iter().flatten().map(|(texture, _)| TextureCache::size_of(texture)).sum()
+	}
+
+	fn insert(&mut self, key: CacheKey, frames: Option<Frames>) {
+		self.bytes += Self::size_of(&frames);
+		if frames.is_some() {
+			self.animated.retain(|animated| animated != &key);
+			self.animated.push_back(key.clone());
+		}
+		if let Some(old) = self.entries.insert(key, frames) {
+			self.bytes -= Self::size_of(&old);
+		}
+		while self.bytes > MAX_ANIMATION_BYTES && self.animated.len() > 1 {
+			if let Some(old) = self.animated.pop_front().and_then(|oldest| self.entries.remove(&oldest)) {
+				self.bytes -= Self::size_of(&old);
+			}
+		}
+	}
+}
+
+thread_local! {
+	static CACHE: RefCell<TextureCache> = RefCell::new(TextureCache::default());
+	static ANIMATIONS: RefCell<AnimationCache> = RefCell::new(AnimationCache::default());
+	/// fingerprints by uuid and source id
+	static FINGERPRINTS: RefCell<HashMap<String, HashMap<u64, u64>>> = RefCell::new(HashMap::new());
+}
+
+pub fn get(key: &CacheKey) -> Option<gdk::Texture> {
+	CACHE.with(|cache| cache.borrow_mut().get(key))
+}
+
+pub fn insert(key: CacheKey, texture: gdk::Texture) {
+	CACHE.with(|cache| cache.borrow_mut().insert(key, texture))
+}
+
+/// The frames of the animation, `Some(None)` if it is known to be still.
+pub fn animation(key: &CacheKey) -> Option<Option<Frames>> {
+	ANIMATIONS.with(|animations| animations.borrow().entries.get(key).cloned())
+}
+
+pub fn insert_animation(key: CacheKey, frames: Option<Frames>) {
+	ANIMATIONS.with(|animations| animations.borrow_mut().insert(key, frames))
+}
@@ -0,0 +1,22 @@
+use gtk::{gio, prelude::*};
+
+pub type FetchResult = Result<Vec<u8>, String>;
+
+/// Retrieves the raw bytes behind a remote artwork uri. Called and
+/// answered on the main thread; implementations have to do their IO
+/// asynchronously.
+pub trait ArtworkFetcher {
+	fn fetch(&self, uri: &str, done: Box<dyn FnOnce(FetchResult)>);
+}
+
+/// Default fetcher, delegating to GIO (and gvfs for http/https).
+#[derive(Debug, Default)]
+pub struct GioFetcher;
+
+impl ArtworkFetcher for GioFetcher {
+	fn fetch(&self, uri: &str, done: Box<dyn FnOnce(FetchResult)>) {
+		gio::File::for_uri(uri).load_contents_async(None::<&gio::Cancellable>, move |res| {
+			done(res.map(|(data, _etag)| data.to_vec()).map_err(|e| e.to_string()))
+		});
+	}
+}
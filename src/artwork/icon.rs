@@ -0,0 +1,63 @@
+use gtk::{gdk, glib, prelude::*};
+use utopia_common::library::artwork::ArtworkData;
+
+use super::{load_artwork, placeholder, Scaling};
+
+/// A resolved provider icon.
+#[derive(Debug, Clone)]
+pub enum ProviderIcon {
+	Named(String),
+	Texture(gdk::Texture)
+}
+
+enum Source {
+	Themed(String),
+	Artwork(ArtworkData)
+}
+
+/// Whether the bytes start like an image any of the decoders know.
+fn is_image(bytes: &[u8]) -> bool {
+	image::guess_format(bytes).is_ok() || bytes.starts_with(b"<svg") || bytes.starts_with(b"<?xml")
+}
+
+/// Makes sense of the `icon` a provider sends: an absolute path, a
+/// `file://` or `data:` uri, base64 encoded image data or the name of a
+/// themed icon. `None` if it's neither.
+fn source(widget: &gtk::Widget, icon: &str) -> Option<Source> {
+	if icon.starts_with('/') {
+		return Some(Source::Artwork(ArtworkData::Path(icon.to_owned().into())));
+	}
+	if icon.starts_with("file:") || icon.starts_with("data:") {
+		return Some(Source::Artwork(ArtworkData::Uri(icon.to_owned())));
+	}
+	let themed = !icon.is_empty() && icon.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+	if themed && gtk::IconTheme::for_display(&widget.display()).has_icon(icon) {
+		return Some(Source::Themed(icon.to_owned()));
+	}
+	let decoded = glib::base64_decode(icon);
+	is_image(&decoded).then(|| Source::Artwork(ArtworkData::Uri(format!("data:;base64,{}", icon))))
+}
+
+/// Loads the icon of a provider at `size` pixels, falling back to a
+/// monogram of its name. Themed icons are left to the widgets.
+pub fn load_provider_icon<F: FnOnce(ProviderIcon) + 'static>(
+	widget: &gtk::Widget,
+	uuid: &str,
+	name: &str,
+	icon: Option<&str>,
+	size: i32,
+	callback: F
+) {
+	let fallback = {
+		let widget = widget.clone();
+		let (uuid, name) = (uuid.to_owned(), name.to_owned());
+		move || ProviderIcon::Texture(placeholder::monogram(&widget, &uuid, &name, size))
+	};
+	match icon.and_then(|icon| source(widget, icon)) {
+		Some(Source::Themed(name)) => callback(ProviderIcon::Named(name)),
+		Some(Source::Artwork(data)) => load_artwork(uuid, &data, size, size, Scaling::Fit, move |texture| {
+			callback(texture.map_or_else(fallback, ProviderIcon::Texture))
+		}),
+		None => callback(fallback())
+	}
+}
@@ -0,0 +1,677 @@
+mod animation;
+mod cache;
+mod fetcher;
+mod icon;
+pub mod overrides;
+mod palette;
+mod placeholder;
+mod raw;
+mod scaled;
+
+use std::{path::{Path, PathBuf},
+          rc::Rc,
+          sync::{mpsc, Arc, Mutex},
+          thread};
+
+use gtk::{gdk,
+          gdk_pixbuf::{Colorspace, Pixbuf, PixbufLoader},
+          gio,
+          glib,
+          prelude::*};
+use animation::Frames;
+use image::{imageops::FilterType, RgbaImage};
+use utopia_common::library::{artwork::{ArtworkData, ArtworkType},
+                             LibraryItemFrontendDetails};
+
+pub use animation::AnimatedTexture;
+pub use fetcher::{ArtworkFetcher, FetchResult, GioFetcher};
+pub use icon::{load_provider_icon, ProviderIcon};
+pub use palette::Palette;
+pub use scaled::ScaledTexture;
+
+pub const PLACEHOLDER: &str = "/dev/sp1rit/Utopia/artwork.svg";
+const WORKERS: usize = 4;
+
+pub fn placeholder(width: i32, height: i32) -> Pixbuf {
+	Pixbuf::from_resource_at_scale(PLACEHOLDER, width, height, false).expect("Failed loading placeholder artwork")
+}
+
+pub fn placeholder_texture(width: i32, height: i32) -> gdk::Texture {
+	gdk::Texture::for_pixbuf(&placeholder(width, height))
+}
+
+/// Generated cover for an item, see [`placeholder::generate`].
+pub fn placeholder_cover<W: IsA<gtk::Widget>>(
+	widget: &W,
+	item: &LibraryItemFrontendDetails,
+	width: i32,
+	height: i32
+) -> gdk::Texture {
+	placeholder::generate(widget.upcast_ref(), &item.uuid, &item.name, width, height)
+}
+
+/// Makes an uuid safe to use in file names.
+fn file_name(uuid: &str) -> String {
+	uuid.chars()
+		.map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+		.collect()
+}
+
+/// How artwork is brought to the requested size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scaling {
+	/// Stretch to exactly the requested size
+	Exact,
+	/// Keep the aspect ratio and fit into the requested size
+	Fit
+}
+
+impl Scaling {
+	fn dimensions(&self, src_width: u32, src_height: u32, width: u32, height: u32) -> (u32, u32) {
+		match self {
+			Self::Exact => (width, height),
+			Self::Fit => {
+				let ratio = f64::min(
+					width as f64 / src_width.max(1) as f64,
+					height as f64 / src_height.max(1) as f64
+				);
+				(
+					((src_width as f64 * ratio).round() as u32).max(1),
+					((src_height as f64 * ratio).round() as u32).max(1)
+				)
+			}
+		}
+	}
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Fixed set of threads doing the decoding and scaling, so a large
+/// library does not spawn a thread per cover.
+struct WorkerPool {
+	jobs: mpsc::Sender<Job>
+}
+impl WorkerPool {
+	fn new(workers: usize) -> Self {
+		let (jobs, queue) = mpsc::channel::<Job>();
+		let queue = Arc::new(Mutex::new(queue));
+		for i in 0..workers {
+			let queue = queue.clone();
+			thread::Builder::new()
+				.name(format!("artwork-{}", i))
+				.spawn(move || loop {
+					let job = queue.lock().unwrap().recv();
+					match job {
+						Ok(job) => job(),
+						Err(_) => break
+					}
+				})
+				.expect("Failed spawning artwork worker");
+		}
+		Self {
+			jobs
+		}
+	}
+
+	fn spawn<F: FnOnce() + Send + 'static>(&self, job: F) {
+		if let Err(e) = self.jobs.send(Box::new(job)) {
+			eprintln!("Artwork workers died: {}", e);
+		}
+	}
+}
+
+struct ArtworkLoader {
+	pool: WorkerPool,
+	fetcher: Rc<dyn ArtworkFetcher>
+}
+
+impl ArtworkLoader {
+	/// `fetcher` retrieves every artwork behind a remote uri.
+	fn new(workers: usize, fetcher: Rc<dyn ArtworkFetcher>) -> Self {
+		Self {
+			pool: WorkerPool::new(workers),
+			fetcher
+		}
+	}
+
+	/// Fetches a remote artwork, errors name the uri.
+	fn fetch<F: FnOnce(FetchResult) + 'static>(&self, uri: &str, done: F) {
+		let failed = uri.to_owned();
+		self.fetcher.fetch(
+			uri,
+			Box::new(move |res| done(res.map_err(|e| format!("fetching {}: {}", failed, e))))
+		);
+	}
+}
+
+thread_local! {
+	static LOADER: ArtworkLoader = {
+		let loader = ArtworkLoader::new(WORKERS, Rc::new(GioFetcher));
+		loader.pool.spawn(cache::prune_thumbnails);
+		loader
+	};
+}
+
+fn fetch<F: FnOnce(FetchResult) + 'static>(uri: &str, done: F) {
+	LOADER.with(|loader| loader.fetch(uri, done))
+}
+
+enum Source {
+	Raw {
+		data: Vec<u8>,
+		has_alpha: bool,
+		bits_per_sample: i32,
+		width: i32,
+		height: i32,
+		rowstride: i32
+	},
+	Encoded(Vec<u8>),
+	File(PathBuf),
+	DataUri(String)
+}
+
+enum Decoded {
+	Image(RgbaImage),
+	// formats the image crate can't handle (svg, ...) are left to
+	// gdk-pixbuf on the main thread
+	Foreign(Vec<u8>)
+}
+
+fn percent_decode(data: &str) -> Vec<u8> {
+	let bytes = data.as_bytes();
+	let mut out = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		if bytes[i] == b'%' && i + 2 < bytes.len() {
+			if let Some(byte) = std::str::from_utf8(&bytes[i + 1..i + 3])
+				.ok()
+				.and_then(|hex| u8::from_str_radix(hex, 16).ok())
+			{
+				out.push(byte);
+				i += 3;
+				continue;
+			}
+		}
+		out.push(bytes[i]);
+		i += 1;
+	}
+	out
+}
+
+fn decode_data_uri(uri: &str) -> Result<Vec<u8>, String> {
+	let (header, data) = uri
+		.strip_prefix("data:")
+		.and_then(|rest| rest.split_once(','))
+		.ok_or_else(|| String::from("malformed data uri"))?;
+	if header.ends_with(";base64") {
+		Ok(glib::base64_decode(&String::from_utf8_lossy(&percent_decode(data))))
+	} else {
+		Ok(percent_decode(data))
+	}
+}
+
+fn read_encoded(source: Source) -> Result<Vec<u8>, String> {
+	match source {
+		Source::Encoded(bytes) => Ok(bytes),
+		Source::File(path) => std::fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e)),
+		Source::DataUri(uri) => decode_data_uri(&uri),
+		Source::Raw {
+			..
+		} => Err(String::from("raw artwork is not encoded"))
+	}
+}
+
+fn decode(
+	source: Source,
+	width: u32,
+	height: u32,
+	scaling: Scaling,
+	thumbnail: Option<&Path>
+) -> Result<Decoded, String> {
+	if let Some(image) = thumbnail
+		.filter(|thumbnail| thumbnail.exists())
+		.and_then(|thumbnail| image::open(thumbnail).ok())
+	{
+		return Ok(Decoded::Image(image.into_rgba8()));
+	}
+
+	let image = match source {
+		Source::Raw {
+			data,
+			has_alpha,
+			bits_per_sample,
+			width,
+			height,
+			rowstride
+		} => raw::decode_raw(&data, has_alpha, bits_per_sample, width, height, rowstride)
+			.map_err(|e| format!("invalid raw artwork: {}", e))?,
+		source => {
+			let bytes = read_encoded(source)?;
+			match image::load_from_memory(&bytes) {
+				Ok(image) => image.into_rgba8(),
+				Err(_) => return Ok(Decoded::Foreign(bytes))
+			}
+		}
+	};
+	let (width, height) = scaling.dimensions(image.width(), image.height(), width, height);
+	let image = image::imageops::resize(&image, width, height, FilterType::Lanczos3);
+	if let Some(thumbnail) = thumbnail {
+		// written aside and moved in place, so an interrupted write never
+		// looks like a thumbnail; per worker, as they might race
+		let partial = thumbnail.with_extension(format!(
+			"png.{}.part",
+			thread::current().name().unwrap_or("main")
+		));
+		let saved = thumbnail
+			.parent()
+			.map_or(Ok(()), std::fs::create_dir_all)
+			.map_err(|e| e.to_string())
+			.and_then(|_| {
+				image
+					.save_with_format(&partial, image::ImageFormat::Png)
+					.map_err(|e| e.to_string())
+			})
+			.and_then(|_| std::fs::rename(&partial, thumbnail).map_err(|e| e.to_string()));
+		if let Err(e) = saved {
+			let _ = std::fs::remove_file(&partial);
+			eprintln!("Failed writing thumbnail {}: {}", thumbnail.display(), e);
+		}
+	}
+	Ok(Decoded::Image(image))
+}
+
+fn to_pixbuf(decoded: Decoded, width: i32, height: i32, scaling: Scaling) -> Result<Pixbuf, String> {
+	match decoded {
+		Decoded::Image(image) => {
+			let (w, h) = image.dimensions();
+			Ok(Pixbuf::from_bytes(
+				&glib::Bytes::from_owned(image.into_raw()),
+				Colorspace::Rgb,
+				true,
+				8,
+				w as i32,
+				h as i32,
+				w as i32 * 4
+			))
+		},
+		Decoded::Foreign(bytes) => {
+			let loader = PixbufLoader::new();
+			loader.connect_size_prepared(move |loader, src_width, src_height| {
+				let (width, height) =
+					scaling.dimensions(src_width as u32, src_height as u32, width as u32, height as u32);
+				loader.set_size(width as i32, height as i32);
+			});
+			loader.write(&bytes).map_err(|e| e.to_string())?;
+			loader.close().map_err(|e| e.to_string())?;
+			loader
+				.pixbuf()
+				.ok_or_else(|| String::from("unknown image format"))
+		}
+	}
+}
+
+type Loaded = Result<Pixbuf, String>;
+
+/// Runs the job on the artwork workers, the callback gets its result
+/// on the main thread.
+pub fn spawn_worker<T, J, F>(job: J, callback: F)
+where
+	T: Send + 'static,
+	J: FnOnce() -> T + Send + 'static,
+	F: FnOnce(Result<T, String>) + 'static
+{
+	let (tx, rx) = futures::channel::oneshot::channel();
+	LOADER.with(|loader| {
+		loader.pool.spawn(move || {
+			let _ = tx.send(job());
+		})
+	});
+	glib::MainContext::default().spawn_local(async move {
+		callback(rx.await.map_err(|_| String::from("artwork worker died")))
+	});
+}
+
+fn decode_async<F: FnOnce(Loaded) + 'static>(
+	source: Source,
+	width: i32,
+	height: i32,
+	scaling: Scaling,
+	thumbnail: Option<PathBuf>,
+	callback: F
+) {
+	spawn_worker(
+		move || decode(source, width as u32, height as u32, scaling, thumbnail.as_deref()),
+		move |decoded| {
+			callback(decoded.and_then(|decoded| decoded.and_then(|decoded| to_pixbuf(decoded, width, height, scaling))))
+		}
+	);
+}
+
+/// Calls back with the frames, `None` for still images.
+fn frames_async<F: FnOnce(Result<Option<Frames>, String>) + 'static>(
+	source: Source,
+	width: i32,
+	height: i32,
+	callback: F
+) {
+	spawn_worker(
+		move || read_encoded(source).and_then(|bytes| animation::decode_frames(&bytes, width as u32, height as u32)),
+		move |frames| {
+			callback(frames.and_then(|frames| frames).map(|frames| {
+				frames.map(|frames| {
+					frames
+						.into_iter()
+						.filter_map(|(image, delay)| {
+							to_pixbuf(Decoded::Image(image), width, height, Scaling::Exact)
+								.ok()
+								.map(|pixbuf| (gdk::Texture::for_pixbuf(&pixbuf), delay))
+						})
+						.collect()
+				})
+			}))
+		}
+	);
+}
+
+fn load_source<F: FnOnce(Loaded) + 'static>(
+	data: &ArtworkData,
+	width: i32,
+	height: i32,
+	scaling: Scaling,
+	thumbnail: Option<PathBuf>,
+	callback: F
+) {
+	match data {
+		ArtworkData::Data(data, has_alpha, bits_per_sample, w, h, rowstride) => decode_async(
+			Source::Raw {
+				data: data.clone(),
+				has_alpha: *has_alpha,
+				bits_per_sample: *bits_per_sample,
+				width: *w,
+				height: *h,
+				rowstride: *rowstride
+			},
+			width,
+			height,
+			scaling,
+			thumbnail,
+			callback
+		),
+		ArtworkData::Path(path) => decode_async(Source::File(PathBuf::from(path)), width, height, scaling, thumbnail, callback),
+		ArtworkData::Uri(uri) if uri.starts_with("data:") => {
+			decode_async(Source::DataUri(uri.clone()), width, height, scaling, thumbnail, callback)
+		},
+		ArtworkData::Uri(uri) => {
+			if let Some(path) = uri
+				.starts_with("file:")
+				.then(|| gio::File::for_uri(uri).path())
+				.flatten()
+			{
+				return decode_async(Source::File(path), width, height, scaling, thumbnail, callback);
+			}
+			// skip the network if there already is a thumbnail
+			if let Some(existing) = thumbnail.as_ref().filter(|thumbnail| thumbnail.exists()) {
+				return decode_async(Source::File(existing.clone()), width, height, scaling, thumbnail, callback);
+			}
+			fetch(uri, move |res| match res {
+				Ok(bytes) => decode_async(Source::Encoded(bytes), width, height, scaling, thumbnail, callback),
+				Err(e) => callback(Err(e))
+			});
+		}
+	}
+}
+
+/// Loads a single artwork of an item through the shared texture
+/// cache, backed by thumbnails in the users cache directory. Calls
+/// back with `None` if the artwork could not be loaded.
+pub fn load_artwork<F: FnOnce(Option<gdk::Texture>) + 'static>(
+	uuid: &str,
+	data: &ArtworkData,
+	width: i32,
+	height: i32,
+	scaling: Scaling,
+	callback: F
+) {
+	let item_uuid = uuid.to_owned();
+	with_fingerprint(uuid, data, move |res| match res {
+		Ok((data, fingerprint)) => {
+			let key = cache::CacheKey::new(&item_uuid, fingerprint, width, height, scaling);
+			load_keyed(key, data, scaling, callback)
+		},
+		Err(e) => {
+			eprintln!("Warning: failed loading artwork of {}: {}", item_uuid, e);
+			callback(None)
+		}
+	});
+}
+
+/// Calls back with the fingerprint of the artwork, computed on the
+/// workers the first time it is asked for.
+fn with_fingerprint<F: FnOnce(Result<(&ArtworkData, u64), String>) + 'static>(
+	uuid: &str,
+	data: &ArtworkData,
+	callback: F
+) {
+	if let Some(fingerprint) = cache::known_fingerprint(uuid, data) {
+		return callback(Ok((data, fingerprint)));
+	}
+	let uuid = uuid.to_owned();
+	let data = data.clone();
+	spawn_worker(
+		move || (cache::fingerprint(&data), data),
+		move |res| match res {
+			Ok((fingerprint, data)) => {
+				cache::remember_fingerprint(&uuid, &data, fingerprint);
+				callback(Ok((&data, fingerprint)))
+			},
+			Err(e) => callback(Err(e))
+		}
+	);
+}
+
+/// Drops what is known about the artwork of an item, to be called
+/// whenever it changed.
+pub fn forget(uuid: &str) {
+	cache::forget_fingerprints(uuid);
+}
+
+fn load_keyed<F: FnOnce(Option<gdk::Texture>) + 'static>(
+	key: cache::CacheKey,
+	data: &ArtworkData,
+	scaling: Scaling,
+	callback: F
+) {
+	if let Some(texture) = cache::get(&key) {
+		return callback(Some(texture));
+	}
+	let (width, height) = (key.width, key.height);
+	let thumbnail = key.thumbnail();
+	load_source(data, width, height, scaling, Some(thumbnail), move |loaded| match loaded {
+		Ok(pixbuf) => {
+			let texture = gdk::Texture::for_pixbuf(&pixbuf);
+			cache::insert(key, texture.clone());
+			callback(Some(texture))
+		},
+		Err(e) => {
+			eprintln!("Warning: failed loading artwork of {}: {}", key.uuid, e);
+			callback(None)
+		}
+	});
+}
+
+/// Loads the cover of an item, preferring the users override over the
+/// case cover of the provider. Falls back to a generated placeholder if
+/// there is no usable cover, which is rendered when the main loop is
+/// idle. The widget provides the fonts for the placeholder.
+pub fn load_cover<W: IsA<gtk::Widget>, F: FnOnce(gdk::Texture) + 'static>(
+	widget: &W,
+	item: &LibraryItemFrontendDetails,
+	width: i32,
+	height: i32,
+	callback: F
+) {
+	let data = overrides::get(&item.uuid).or_else(|| {
+		item.details
+			.artworks
+			.iter()
+			.rev()
+			.find(|artwork| matches!(artwork.r#type, ArtworkType::CaseCover))
+			.map(|artwork| artwork.data.clone())
+	});
+	match data {
+		Some(data) => {
+			let widget = widget.clone().upcast::<gtk::Widget>();
+			let (uuid, name) = (item.uuid.clone(), item.name.clone());
+			load_artwork(&item.uuid, &data, width, height, Scaling::Exact, move |texture| match texture {
+				Some(texture) => callback(texture),
+				None => placeholder::generate_idle(&widget, &uuid, &name, width, height, callback)
+			})
+		},
+		None => placeholder::generate_idle(widget.upcast_ref(), &item.uuid, &item.name, width, height, callback)
+	}
+}
+
+/// Extracts the palette of a texture on the artwork workers.
+pub fn load_palette<F: FnOnce(Option<Palette>) + 'static>(texture: &gdk::Texture, callback: F) {
+	let (width, height) = (texture.width() as usize, texture.height() as usize);
+	let stride = width * 4;
+	let mut pixels = vec![0; stride * height];
+	texture.download(&mut pixels, stride);
+	spawn_worker(
+		move || palette::extract(&palette::from_argb(&pixels, width as u32, height as u32, stride)),
+		move |palette| callback(palette.ok().flatten())
+	);
+}
+
+/// Loads the case cover of an item as animation, if it is an animated
+/// GIF or APNG. Calls back with `None` for still covers. Decoding every
+/// frame is expensive, so this is meant to be done on demand only; the
+/// most recent animations are kept around.
+pub fn load_animation<F: FnOnce(Option<AnimatedTexture>) + 'static>(
+	item: &LibraryItemFrontendDetails,
+	width: i32,
+	height: i32,
+	scale: i32,
+	callback: F
+) {
+	// overrides are stored as still image
+	if overrides::get(&item.uuid).is_some() {
+		return callback(None);
+	}
+	let artwork = item
+		.details
+		.artworks
+		.iter()
+		.rev()
+		.find(|artwork| matches!(artwork.r#type, ArtworkType::CaseCover));
+	let data = match artwork.map(|artwork| &artwork.data) {
+		// raw pixel data can't be animated
+		Some(ArtworkData::Data(..)) | None => return callback(None),
+		Some(data) => data
+	};
+	let uuid = item.uuid.clone();
+	with_fingerprint(&item.uuid, data, move |res| {
+		let (data, fingerprint) = match res {
+			Ok(res) => res,
+			Err(e) => {
+				eprintln!("Failed loading animated artwork of {}: {}", uuid, e);
+				return callback(None);
+			}
+		};
+		let key = cache::CacheKey::new(&uuid, fingerprint, width, height, Scaling::Exact);
+		if let Some(frames) = cache::animation(&key) {
+			return callback(frames.map(|frames| AnimatedTexture::new(frames, scale)));
+		}
+		load_frames(data, width, height, move |res| match res {
+			Ok(frames) => {
+				cache::insert_animation(key, frames.clone());
+				callback(frames.map(|frames| AnimatedTexture::new(frames, scale)))
+			},
+			Err(e) => {
+				eprintln!("Failed loading animated artwork of {}: {}", uuid, e);
+				callback(None)
+			}
+		})
+	});
+}
+
+fn load_frames<F: FnOnce(Result<Option<Frames>, String>) + 'static>(
+	data: &ArtworkData,
+	width: i32,
+	height: i32,
+	callback: F
+) {
+	match data {
+		ArtworkData::Path(path) => frames_async(Source::File(PathBuf::from(path)), width, height, callback),
+		ArtworkData::Uri(uri) if uri.starts_with("data:") => {
+			frames_async(Source::DataUri(uri.clone()), width, height, callback)
+		},
+		ArtworkData::Uri(uri) => {
+			if let Some(path) = uri
+				.starts_with("file:")
+				.then(|| gio::File::for_uri(uri).path())
+				.flatten()
+			{
+				return frames_async(Source::File(path), width, height, callback);
+			}
+			fetch(uri, move |res| match res {
+				Ok(bytes) => frames_async(Source::Encoded(bytes), width, height, callback),
+				Err(e) => callback(Err(e))
+			});
+		},
+		ArtworkData::Data(..) => callback(Ok(None))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::RefCell;
+
+	use super::*;
+
+	/// Answers right away, remembering what was asked for.
+	#[derive(Default)]
+	struct StubFetcher {
+		requested: RefCell<Vec<String>>
+	}
+
+	impl ArtworkFetcher for StubFetcher {
+		fn fetch(&self, uri: &str, done: Box<dyn FnOnce(FetchResult)>) {
+			self.requested.borrow_mut().push(uri.to_owned());
+			match uri {
+				"https://example.org/cover.png" => done(Ok(vec![0x89, b'P', b'N', b'G'])),
+				_ => done(Err(String::from("not found")))
+			}
+		}
+	}
+
+	fn fetch_with(fetcher: &Rc<StubFetcher>, uri: &str) -> Option<FetchResult> {
+		// no workers, fetching doesn't need them
+		let loader = ArtworkLoader::new(0, fetcher.clone());
+		let result = Rc::new(RefCell::new(None));
+		let answer = result.clone();
+		loader.fetch(uri, move |res| {
+			answer.replace(Some(res));
+		});
+		result.take()
+	}
+
+	#[test]
+	fn fetches_through_the_given_fetcher() {
+		let fetcher = Rc::new(StubFetcher::default());
+		assert_eq!(
+			fetch_with(&fetcher, "https://example.org/cover.png"),
+			Some(Ok(vec![0x89, b'P', b'N', b'G']))
+		);
+		assert_eq!(*fetcher.requested.borrow(), vec![String::from("https://example.org/cover.png")]);
+	}
+
+	#[test]
+	fn fetch_errors_name_the_uri() {
+		let fetcher = Rc::new(StubFetcher::default());
+		assert_eq!(
+			fetch_with(&fetcher, "https://example.org/missing.png"),
+			Some(Err(String::from("fetching https://example.org/missing.png: not found")))
+		);
+	}
+}
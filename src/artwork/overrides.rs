@@ -0,0 +1,106 @@
+use std::{cell::RefCell, path::{Path, PathBuf}, rc::Rc};
+
+use gtk::{gio, glib, prelude::*};
+use image::{imageops::FilterType, DynamicImage, ImageFormat};
+use utopia_common::library::artwork::ArtworkData;
+
+use super::{ArtworkFetcher, GioFetcher};
+
+/// Overrides taller than this are scaled down when stored.
+const MAX_HEIGHT: u32 = 1500;
+
+thread_local! {
+	static LISTENERS: RefCell<Vec<Rc<dyn Fn(&str)>>> = RefCell::new(Vec::new());
+}
+
+pub fn dir() -> PathBuf {
+	glib::user_data_dir().join("gtopia").join("artwork")
+}
+
+pub fn path(uuid: &str) -> PathBuf {
+	dir().join(format!("{}.png", super::file_name(uuid)))
+}
+
+/// The cover the user picked for an item, if any.
+pub fn get(uuid: &str) -> Option<ArtworkData> {
+	let path = path(uuid);
+	path.exists()
+		.then(|| ArtworkData::Path(path.to_string_lossy().into_owned().into()))
+}
+
+/// Calls `f` with the uuid of every item whose override got set or
+/// removed.
+pub fn connect_changed<F: Fn(&str) + 'static>(f: F) {
+	LISTENERS.with(|listeners| listeners.borrow_mut().push(Rc::new(f)));
+}
+
+fn notify(uuid: &str) {
+	// the override keeps its path, only the file changed
+	super::forget(uuid);
+	// listeners may reload artwork, so don't hold the borrow
+	let listeners = LISTENERS.with(|listeners| listeners.borrow().clone());
+	for listener in listeners {
+		listener(uuid);
+	}
+}
+
+/// Cuts the largest centered 2:3 region, the aspect ratio of the cards,
+/// out of the image.
+fn crop(image: DynamicImage) -> DynamicImage {
+	let (width, height) = (image.width(), image.height());
+	let (crop_width, crop_height) = if width * 3 > height * 2 {
+		((height * 2 / 3).max(1), height)
+	} else {
+		(width, (width * 3 / 2).max(1))
+	};
+	let image = image.crop_imm((width - crop_width) / 2, (height - crop_height) / 2, crop_width, crop_height);
+	if crop_height > MAX_HEIGHT {
+		image.resize_exact(MAX_HEIGHT * 2 / 3, MAX_HEIGHT, FilterType::Lanczos3)
+	} else {
+		image
+	}
+}
+
+fn store(bytes: &[u8], target: &Path) -> Result<(), String> {
+	let image = image::load_from_memory(bytes).map_err(|e| e.to_string())?;
+	std::fs::create_dir_all(dir()).map_err(|e| e.to_string())?;
+	// readers must never see a half written cover
+	let partial = target.with_extension("png.part");
+	crop(image)
+		.save_with_format(&partial, ImageFormat::Png)
+		.map_err(|e| e.to_string())?;
+	std::fs::rename(&partial, target).map_err(|e| e.to_string())
+}
+
+/// Crops the image to the card aspect ratio and stores it as cover
+/// override of the item.
+pub fn set<F: FnOnce(Result<(), String>) + 'static>(uuid: &str, file: &gio::File, callback: F) {
+	let uuid = uuid.to_owned();
+	GioFetcher.fetch(
+		&file.uri(),
+		Box::new(move |res| match res {
+			Ok(bytes) => {
+				let target = path(&uuid);
+				super::spawn_worker(
+					move || store(&bytes, &target),
+					move |res| {
+						let res = res.and_then(|res| res);
+						if res.is_ok() {
+							notify(&uuid);
+						}
+						callback(res)
+					}
+				)
+			},
+			Err(e) => callback(Err(e))
+		})
+	);
+}
+
+/// Removes the cover override of the item, falling back to the
+/// artwork of the provider.
+pub fn remove(uuid: &str) -> Result<(), String> {
+	std::fs::remove_file(path(uuid)).map_err(|e| e.to_string())?;
+	notify(uuid);
+	Ok(())
+}
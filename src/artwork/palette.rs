@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use image::{imageops, Rgba, RgbaImage};
+
+/// Pixels sampled along each axis at most, covers are big but their
+/// colors don't need every pixel.
+const SAMPLES: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl Color {
+	pub const BLACK: Self = Self(0, 0, 0);
+	pub const WHITE: Self = Self(255, 255, 255);
+
+	/// Relative luminance as defined by WCAG 2.
+	pub fn luminance(&self) -> f64 {
+		fn channel(value: u8) -> f64 {
+			let value = value as f64 / 255.0;
+			if value <= 0.03928 {
+				value / 12.92
+			} else {
+				((value + 0.055) / 1.055).powf(2.4)
+			}
+		}
+		0.2126 * channel(self.0) + 0.7152 * channel(self.1) + 0.0722 * channel(self.2)
+	}
+
+	pub fn contrast(&self, other: &Self) -> f64 {
+		let (a, b) = (self.luminance(), other.luminance());
+		(a.max(b) + 0.05) / (a.min(b) + 0.05)
+	}
+
+	/// Black or white, whichever is more readable on this color. One
+	/// of the two always reaches the 4.5:1 WCAG AA asks for.
+	pub fn text_color(&self) -> Self {
+		if self.contrast(&Self::WHITE) >= self.contrast(&Self::BLACK) {
+			Self::WHITE
+		} else {
+			Self::BLACK
+		}
+	}
+
+	fn saturation(&self) -> f64 {
+		let max = self.0.max(self.1).max(self.2) as f64;
+		let min = self.0.min(self.1).min(self.2) as f64;
+		if max == 0.0 {
+			0.0
+		} else {
+			(max - min) / max
+		}
+	}
+
+	/// Mixes the color with `other`, `amount` being the share of `other`.
+	pub fn mix(&self, other: &Self, amount: f64) -> Self {
+		let mix = |a: u8, b: u8| (a as f64 * (1.0 - amount) + b as f64 * amount).round() as u8;
+		Self(mix(self.0, other.0), mix(self.1, other.1), mix(self.2, other.2))
+	}
+
+	pub fn to_css(&self) -> String {
+		format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+	/// Most common color of the image
+	pub dominant: Color,
+	/// Most common vivid color, falls back to the dominant one for
+	/// grayish images
+	pub accent: Color
+}
+
+/// Turns premultiplied, native endian ARGB pixels, as downloaded from a
+/// texture, into an image.
+pub fn from_argb(pixels: &[u8], width: u32, height: u32, stride: usize) -> RgbaImage {
+	RgbaImage::from_fn(width, height, |x, y| {
+		let offset = y as usize * stride + x as usize * 4;
+		let pixel = u32::from_ne_bytes([pixels[offset], pixels[offset + 1], pixels[offset + 2], pixels[offset + 3]]);
+		let alpha = pixel >> 24;
+		let unpremultiply = |value: u32| {
+			if alpha == 0 {
+				0
+			} else {
+				((value & 0xff) * 255 / alpha).min(255) as u8
+			}
+		};
+		Rgba([unpremultiply(pixel >> 16), unpremultiply(pixel >> 8), unpremultiply(pixel), alpha as u8])
+	})
+}
+
+/// Computes the palette of the image. Returns `None` for fully
+/// transparent images.
+pub fn extract(image: &RgbaImage) -> Option<Palette> {
+	let (width, height) = image.dimensions();
+	if width == 0 || height == 0 {
+		return None;
+	}
+	let scale = (SAMPLES as f64 / width.max(height) as f64).min(1.0);
+	let sampled = imageops::thumbnail(
+		image,
+		((width as f64 * scale).round() as u32).max(1),
+		((height as f64 * scale).round() as u32).max(1)
+	);
+	// 4 bits per channel, summing up the exact colors of each bucket
+	let mut buckets: HashMap<u16, (u32, [u32; 3])> = HashMap::new();
+	for Rgba([r, g, b, alpha]) in sampled.pixels() {
+		if *alpha < 128 {
+			continue;
+		}
+		let (r, g, b) = (*r as u32, *g as u32, *b as u32);
+		let bucket = buckets
+			.entry(((r >> 4) << 8 | (g >> 4) << 4 | b >> 4) as u16)
+			.or_insert((0, [0; 3]));
+		bucket.0 += 1;
+		bucket.1[0] += r;
+		bucket.1[1] += g;
+		bucket.1[2] += b;
+	}
+
+	let colors: Vec<(u32, Color)> = buckets
+		.values()
+		.map(|(count, sum)| {
+			(
+				*count,
+				Color((sum[0] / count) as u8, (sum[1] / count) as u8, (sum[2] / count) as u8)
+			)
+		})
+		.collect();
+	let dominant = colors.iter().max_by_key(|(count, _)| *count)?.1;
+	let accent = colors
+		.iter()
+		.filter(|(_, color)| color.saturation() >= 0.3)
+		.max_by(|(a_count, a), (b_count, b)| {
+			(*a_count as f64 * a.saturation())
+				.partial_cmp(&(*b_count as f64 * b.saturation()))
+				.unwrap()
+		})
+		.map_or(dominant, |(_, color)| *color);
+	Some(Palette {
+		dominant,
+		accent
+	})
+}
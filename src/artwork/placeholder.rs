@@ -0,0 +1,192 @@
+use std::cell::RefCell;
+
+use gtk::{gdk, glib, graphene, gsk, pango, prelude::*};
+
+use super::cache;
+
+thread_local! {
+	/// Realized once and kept, setting up a renderer per placeholder
+	/// is what makes them expensive.
+	static RENDERER: RefCell<Option<gsk::CairoRenderer>> = RefCell::new(None);
+}
+
+/// Renders the node offscreen with the shared renderer.
+fn render_node(node: &gsk::RenderNode, bounds: &graphene::Rect) -> Option<gdk::Texture> {
+	RENDERER.with(|renderer| {
+		let mut renderer = renderer.borrow_mut();
+		if renderer.is_none() {
+			let cairo = gsk::CairoRenderer::new();
+			if let Err(e) = cairo.realize(None) {
+				eprintln!("Failed setting up placeholder renderer: {}", e);
+				return None;
+			}
+			*renderer = Some(cairo);
+		}
+		renderer.as_ref().unwrap().render_texture(node, Some(bounds))
+	})
+}
+
+/// Stable across runs and platforms, unlike the std hashers.
+fn fnv1a(text: &str) -> u32 {
+	text.bytes()
+		.fold(0x811c9dc5, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x01000193))
+}
+
+fn hsl(hue: f32, saturation: f32, lightness: f32) -> gdk::RGBA {
+	let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+	let h = hue / 60.0;
+	let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+	let (r, g, b) = match h as u32 {
+		0 => (chroma, x, 0.0),
+		1 => (x, chroma, 0.0),
+		2 => (0.0, chroma, x),
+		3 => (0.0, x, chroma),
+		4 => (x, 0.0, chroma),
+		_ => (chroma, 0.0, x)
+	};
+	let m = lightness - chroma / 2.0;
+	gdk::RGBA {
+		red: r + m,
+		green: g + m,
+		blue: b + m,
+		alpha: 1.0
+	}
+}
+
+/// Gradient colors of a placeholder. Dark enough for the white title
+/// to stay readable at any hue.
+fn colors(uuid: &str) -> (gdk::RGBA, gdk::RGBA) {
+	let hash = fnv1a(uuid);
+	let hue = (hash % 360) as f32;
+	let saturation = 0.45 + ((hash >> 16) % 20) as f32 / 100.0;
+	(hsl(hue, saturation, 0.36), hsl((hue + 24.0) % 360.0, saturation, 0.22))
+}
+
+fn render(widget: &gtk::Widget, uuid: &str, name: &str, width: i32, height: i32) -> Option<gdk::Texture> {
+	let snapshot = gtk::Snapshot::new();
+	let bounds = graphene::Rect::new(0.0, 0.0, width as f32, height as f32);
+	let (top, bottom) = colors(uuid);
+	snapshot.append_linear_gradient(
+		&bounds,
+		&graphene::Point::new(0.0, 0.0),
+		&graphene::Point::new(width as f32, height as f32),
+		&[gsk::ColorStop::new(0.0, top), gsk::ColorStop::new(1.0, bottom)]
+	);
+
+	let margin = width / 10;
+	let layout = widget.create_pango_layout(Some(name));
+	let mut font = pango::FontDescription::new();
+	font.set_weight(pango::Weight::Bold);
+	font.set_absolute_size(height as f64 / 14.0 * pango::SCALE as f64);
+	layout.set_font_description(Some(&font));
+	layout.set_width((width - 2 * margin) * pango::SCALE);
+	// negative heights limit the number of lines
+	layout.set_height(-5);
+	layout.set_wrap(pango::WrapMode::WordChar);
+	layout.set_ellipsize(pango::EllipsizeMode::End);
+	layout.set_alignment(pango::Alignment::Center);
+	let (_, text_height) = layout.pixel_size();
+	snapshot.translate(&graphene::Point::new(margin as f32, (height - text_height) as f32 / 2.0));
+	snapshot.append_layout(&layout, &gdk::RGBA {
+		red: 1.0,
+		green: 1.0,
+		blue: 1.0,
+		alpha: 0.95
+	});
+
+	render_node(&snapshot.to_node()?, &bounds)
+}
+
+/// Up to two letters standing for the name, one of each of its first
+/// words.
+fn initials(name: &str) -> String {
+	name.split(|c: char| !c.is_alphanumeric())
+		.filter_map(|word| word.chars().next())
+		.take(2)
+		.flat_map(char::to_uppercase)
+		.collect()
+}
+
+fn render_monogram(widget: &gtk::Widget, uuid: &str, name: &str, size: i32) -> Option<gdk::Texture> {
+	let snapshot = gtk::Snapshot::new();
+	let bounds = graphene::Rect::new(0.0, 0.0, size as f32, size as f32);
+	let (top, bottom) = colors(uuid);
+	snapshot.push_rounded_clip(&gsk::RoundedRect::from_rect(bounds.clone(), size as f32 / 2.0));
+	snapshot.append_linear_gradient(
+		&bounds,
+		&graphene::Point::new(0.0, 0.0),
+		&graphene::Point::new(size as f32, size as f32),
+		&[gsk::ColorStop::new(0.0, top), gsk::ColorStop::new(1.0, bottom)]
+	);
+
+	let layout = widget.create_pango_layout(Some(&initials(name)));
+	let mut font = pango::FontDescription::new();
+	font.set_weight(pango::Weight::Bold);
+	font.set_absolute_size(size as f64 * 0.4 * pango::SCALE as f64);
+	layout.set_font_description(Some(&font));
+	let (text_width, text_height) = layout.pixel_size();
+	snapshot.translate(&graphene::Point::new(
+		(size - text_width) as f32 / 2.0,
+		(size - text_height) as f32 / 2.0
+	));
+	snapshot.append_layout(&layout, &gdk::RGBA {
+		red: 1.0,
+		green: 1.0,
+		blue: 1.0,
+		alpha: 0.95
+	});
+	snapshot.pop();
+
+	render_node(&snapshot.to_node()?, &bounds)
+}
+
+/// Icon for providers without a usable one: the initials of the name on
+/// a circle colored like the placeholder covers.
+pub fn monogram(widget: &gtk::Widget, uuid: &str, name: &str, size: i32) -> gdk::Texture {
+	let key = cache::CacheKey::monogram(uuid, name, size);
+	if let Some(texture) = cache::get(&key) {
+		return texture;
+	}
+	match render_monogram(widget, uuid, name, size) {
+		Some(texture) => {
+			cache::insert(key, texture.clone());
+			texture
+		},
+		None => super::placeholder_texture(size, size)
+	}
+}
+
+/// Cover for items without one: a gradient derived from the uuid with
+/// the title typeset on it, rendered offscreen at the given size.
+/// Falls back to the generic placeholder if rendering fails.
+pub fn generate(widget: &gtk::Widget, uuid: &str, name: &str, width: i32, height: i32) -> gdk::Texture {
+	let key = cache::CacheKey::placeholder(uuid, name, width, height);
+	if let Some(texture) = cache::get(&key) {
+		return texture;
+	}
+	match render(widget, uuid, name, width, height) {
+		Some(texture) => {
+			cache::insert(key, texture.clone());
+			texture
+		},
+		None => super::placeholder_texture(width, height)
+	}
+}
+
+/// Like [`generate`], but renders once the main loop is idle, so that a
+/// screen full of cover-less games doesn't hold up scrolling.
+pub fn generate_idle<F: FnOnce(gdk::Texture) + 'static>(
+	widget: &gtk::Widget,
+	uuid: &str,
+	name: &str,
+	width: i32,
+	height: i32,
+	callback: F
+) {
+	if let Some(texture) = cache::get(&cache::CacheKey::placeholder(uuid, name, width, height)) {
+		return callback(texture);
+	}
+	let widget = widget.clone();
+	let (uuid, name) = (uuid.to_owned(), name.to_owned());
+	glib::idle_add_local_once(move || callback(generate(&widget, &uuid, &name, width, height)));
+}
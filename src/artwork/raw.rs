@@ -0,0 +1,242 @@
+use std::fmt;
+
+use image::{DynamicImage, ImageBuffer, Luma, LumaA, Rgb, Rgba, RgbaImage};
+
+/// Artwork larger than this is rejected instead of allocated.
+const MAX_DIMENSION: i32 = 16384;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RawError {
+	Dimensions(i32, i32),
+	BitsPerSample(i32),
+	Rowstride(i32, usize),
+	AmbiguousLayout(i32, i32),
+	BufferSize(usize, usize)
+}
+
+impl fmt::Display for RawError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Dimensions(width, height) => write!(f, "invalid dimensions {}x{}", width, height),
+			Self::BitsPerSample(bits) => write!(f, "unsupported {} bits per sample", bits),
+			Self::Rowstride(rowstride, min) => write!(f, "rowstride {} is smaller than a row ({} bytes)", rowstride, min),
+			Self::AmbiguousLayout(rowstride, width) => write!(
+				f,
+				"rowstride {} fits neither a color nor a grayscale row of {} pixels",
+				rowstride, width
+			),
+			Self::BufferSize(len, needed) => write!(f, "buffer has {} bytes, {} are needed", len, needed)
+		}
+	}
+}
+
+impl std::error::Error for RawError {}
+
+/// Pixel layout, deduced from the alpha flag and how many bytes a row
+/// takes up. The protocol doesn't tell, so anything that could be color
+/// is taken as color, which is what the daemon sends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Layout {
+	Gray,
+	GrayAlpha,
+	Rgb,
+	Rgba
+}
+
+impl Layout {
+	fn channels(&self) -> usize {
+		match self {
+			Self::Gray => 1,
+			Self::GrayAlpha => 2,
+			Self::Rgb => 3,
+			Self::Rgba => 4
+		}
+	}
+}
+
+/// Strips the rowstride padding off the buffer, returning tightly
+/// packed rows.
+fn pack(data: &[u8], row_len: usize, rowstride: usize, height: usize) -> Vec<u8> {
+	let mut packed = Vec::with_capacity(row_len * height);
+	for y in 0..height {
+		packed.extend_from_slice(&data[y * rowstride..y * rowstride + row_len]);
+	}
+	packed
+}
+
+/// 16 bit samples are sent big endian, as in PNG.
+fn to_u16(data: Vec<u8>) -> Vec<u16> {
+	data.chunks_exact(2)
+		.map(|sample| u16::from_be_bytes([sample[0], sample[1]]))
+		.collect()
+}
+
+/// Converts raw pixel data as supplied by the daemon into an RGBA
+/// image, validating every value first. Supports 8 and 16 bit
+/// grayscale, grayscale with alpha, RGB and RGBA.
+pub fn decode_raw(
+	data: &[u8],
+	has_alpha: bool,
+	bits_per_sample: i32,
+	width: i32,
+	height: i32,
+	rowstride: i32
+) -> Result<RgbaImage, RawError> {
+	if width <= 0 || height <= 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+		return Err(RawError::Dimensions(width, height));
+	}
+	let sample_size = match bits_per_sample {
+		8 => 1,
+		16 => 2,
+		bits => return Err(RawError::BitsPerSample(bits))
+	};
+	let (w, h) = (width as usize, height as usize);
+
+	let (color, gray) = if has_alpha {
+		(Layout::Rgba, Layout::GrayAlpha)
+	} else {
+		(Layout::Rgb, Layout::Gray)
+	};
+	let gray_row = w * gray.channels() * sample_size;
+	if rowstride <= 0 || (rowstride as usize) < gray_row {
+		return Err(RawError::Rowstride(rowstride, gray_row));
+	}
+	// padding says nothing about the channels, so grayscale has to match
+	// exactly: either by its rowstride or by tightly packed rows
+	let (layout, rowstride) = match rowstride as usize {
+		stride if stride >= w * color.channels() * sample_size => (color, stride),
+		stride if stride == gray_row => (gray, stride),
+		_ if data.len() == gray_row * h => (gray, gray_row),
+		_ => return Err(RawError::AmbiguousLayout(rowstride, width))
+	};
+
+	let row_len = w * layout.channels() * sample_size;
+	let needed = rowstride
+		.checked_mul(h - 1)
+		.and_then(|size| size.checked_add(row_len))
+		.unwrap_or(usize::MAX);
+	if data.len() < needed {
+		return Err(RawError::BufferSize(data.len(), needed));
+	}
+
+	let packed = pack(data, row_len, rowstride, h);
+	let (width, height) = (width as u32, height as u32);
+	// the buffer sizes were checked above, so constructing can't fail
+	let image = match (layout, sample_size) {
+		(Layout::Gray, 1) => DynamicImage::ImageLuma8(ImageBuffer::<Luma<u8>, _>::from_raw(width, height, packed).unwrap()),
+		(Layout::GrayAlpha, 1) => {
+			DynamicImage::ImageLumaA8(ImageBuffer::<LumaA<u8>, _>::from_raw(width, height, packed).unwrap())
+		},
+		(Layout::Rgb, 1) => DynamicImage::ImageRgb8(ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, packed).unwrap()),
+		(Layout::Rgba, 1) => DynamicImage::ImageRgba8(ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, packed).unwrap()),
+		(Layout::Gray, _) => {
+			DynamicImage::ImageLuma16(ImageBuffer::<Luma<u16>, _>::from_raw(width, height, to_u16(packed)).unwrap())
+		},
+		(Layout::GrayAlpha, _) => {
+			DynamicImage::ImageLumaA16(ImageBuffer::<LumaA<u16>, _>::from_raw(width, height, to_u16(packed)).unwrap())
+		},
+		(Layout::Rgb, _) => {
+			DynamicImage::ImageRgb16(ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, to_u16(packed)).unwrap())
+		},
+		(Layout::Rgba, _) => {
+			DynamicImage::ImageRgba16(ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, to_u16(packed)).unwrap())
+		}
+	};
+	Ok(image.into_rgba8())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pixel(image: &RgbaImage, x: u32, y: u32) -> [u8; 4] {
+		image.get_pixel(x, y).0
+	}
+
+	#[test]
+	fn decodes_tight_rgb() {
+		let data = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+		let image = decode_raw(&data, false, 8, 2, 2, 6).unwrap();
+		assert_eq!(image.dimensions(), (2, 2));
+		assert_eq!(pixel(&image, 0, 0), [255, 0, 0, 255]);
+		assert_eq!(pixel(&image, 1, 0), [0, 255, 0, 255]);
+		assert_eq!(pixel(&image, 1, 1), [255, 255, 255, 255]);
+	}
+
+	#[test]
+	fn strips_row_padding() {
+		// the last row doesn't need its padding
+		let data = [1, 2, 3, 4, 9, 9, 9, 9, 5, 6, 7, 8];
+		let image = decode_raw(&data, true, 8, 1, 2, 8).unwrap();
+		assert_eq!(pixel(&image, 0, 0), [1, 2, 3, 4]);
+		assert_eq!(pixel(&image, 0, 1), [5, 6, 7, 8]);
+	}
+
+	#[test]
+	fn decodes_grayscale_by_rowstride() {
+		let image = decode_raw(&[0, 128, 255], false, 8, 3, 1, 3).unwrap();
+		assert_eq!(pixel(&image, 1, 0), [128, 128, 128, 255]);
+
+		let image = decode_raw(&[100, 50], true, 8, 1, 1, 2).unwrap();
+		assert_eq!(pixel(&image, 0, 0), [100, 100, 100, 50]);
+	}
+
+	#[test]
+	fn decodes_tightly_packed_grayscale() {
+		// the rowstride fits neither layout, but the buffer is exactly
+		// two gray rows
+		let image = decode_raw(&[10, 20, 30, 40], false, 8, 2, 2, 4).unwrap();
+		assert_eq!(pixel(&image, 0, 1), [30, 30, 30, 255]);
+	}
+
+	#[test]
+	fn rejects_ambiguous_layouts() {
+		assert_eq!(
+			decode_raw(&[0; 8], false, 8, 2, 2, 4),
+			Err(RawError::AmbiguousLayout(4, 2))
+		);
+	}
+
+	#[test]
+	fn reads_16_bit_samples_big_endian() {
+		let image = decode_raw(&[0xff, 0x00, 0x00, 0xff, 0xff, 0xff], false, 16, 1, 1, 6).unwrap();
+		let [r, g, b, a] = pixel(&image, 0, 0);
+		assert!(r > 250, "{}", r);
+		assert!(g < 5, "{}", g);
+		assert_eq!((b, a), (255, 255));
+	}
+
+	#[test]
+	fn rejects_invalid_dimensions() {
+		for &(width, height) in &[(0, 1), (1, 0), (-1, 1), (1, -5), (MAX_DIMENSION + 1, 1)] {
+			assert_eq!(
+				decode_raw(&[0; 16], false, 8, width, height, 3),
+				Err(RawError::Dimensions(width, height))
+			);
+		}
+	}
+
+	#[test]
+	fn rejects_unsupported_bits_per_sample() {
+		for &bits in &[0, 1, 4, 12, 32, -8] {
+			assert_eq!(decode_raw(&[0; 16], false, bits, 1, 1, 3), Err(RawError::BitsPerSample(bits)));
+		}
+	}
+
+	#[test]
+	fn rejects_short_rowstrides() {
+		assert_eq!(decode_raw(&[0; 16], false, 8, 2, 2, 1), Err(RawError::Rowstride(1, 2)));
+		assert_eq!(decode_raw(&[0; 16], false, 8, 2, 2, 0), Err(RawError::Rowstride(0, 2)));
+		assert_eq!(decode_raw(&[0; 16], false, 8, 2, 2, -6), Err(RawError::Rowstride(-6, 2)));
+	}
+
+	#[test]
+	fn rejects_short_buffers() {
+		assert_eq!(decode_raw(&[0; 11], false, 8, 2, 2, 6), Err(RawError::BufferSize(11, 12)));
+		// checked before anything is allocated
+		assert_eq!(
+			decode_raw(&[0; 4], true, 8, MAX_DIMENSION, MAX_DIMENSION, MAX_DIMENSION * 4),
+			Err(RawError::BufferSize(4, 1 << 30))
+		);
+	}
+}
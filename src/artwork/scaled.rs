@@ -0,0 +1,68 @@
+use gtk::{gdk, gdk::subclass::prelude::*, glib, prelude::*, subclass::prelude::*};
+
+mod imp {
+	use std::cell::{Cell, RefCell};
+
+	use super::*;
+
+	#[derive(Debug, Default)]
+	pub struct ScaledTexture {
+		pub texture: RefCell<Option<gdk::Texture>>,
+		pub scale: Cell<i32>
+	}
+
+	#[glib::object_subclass]
+	impl ObjectSubclass for ScaledTexture {
+		type Interfaces = (gdk::Paintable,);
+		type ParentType = glib::Object;
+		type Type = super::ScaledTexture;
+
+		const NAME: &'static str = "UtopiaScaledTexture";
+	}
+
+	impl ObjectImpl for ScaledTexture {}
+
+	impl PaintableImpl for ScaledTexture {
+		fn intrinsic_width(&self, _paintable: &Self::Type) -> i32 {
+			self.texture
+				.borrow()
+				.as_ref()
+				.map_or(0, |texture| texture.width() / self.scale.get().max(1))
+		}
+
+		fn intrinsic_height(&self, _paintable: &Self::Type) -> i32 {
+			self.texture
+				.borrow()
+				.as_ref()
+				.map_or(0, |texture| texture.height() / self.scale.get().max(1))
+		}
+
+		fn snapshot(&self, _paintable: &Self::Type, snapshot: &gdk::Snapshot, width: f64, height: f64) {
+			if let Some(texture) = self.texture.borrow().as_ref() {
+				texture.snapshot(snapshot, width, height);
+			}
+		}
+	}
+}
+
+glib::wrapper! {
+	/// A texture rendered for a given scale factor, reporting its size
+	/// in logical pixels, so that widgets don't blow it up.
+	pub struct ScaledTexture(ObjectSubclass<imp::ScaledTexture>)
+		@implements gdk::Paintable;
+}
+
+impl ScaledTexture {
+	pub fn new(texture: &gdk::Texture, scale: i32) -> Self {
+		let paintable: Self = glib::Object::new(&[]).expect("Failed to create ScaledTexture");
+		let self_ = imp::ScaledTexture::from_instance(&paintable);
+		self_.texture.replace(Some(texture.clone()));
+		self_.scale.set(scale);
+		paintable
+	}
+
+	pub fn texture(&self) -> Option<gdk::Texture> {
+		let self_ = imp::ScaledTexture::from_instance(self);
+		self_.texture.borrow().clone()
+	}
+}
@@ -0,0 +1,244 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use gtk::{gio, glib, glib::ToVariant};
+use once_cell::unsync::OnceCell;
+use utopia_common::library::{LibraryItemFrontend, LibraryItemFrontendDetails, LibraryItemProviderQuitActions,
+                             LibraryItemStatus};
+
+use crate::{store::is_running,
+            uev::{UtopiaMessage, UtopiaRequest}};
+
+const INTERFACE_NAME: &str = "dev.sp1rit.Gtopia.Library";
+const INTROSPECTION: &str = r#"
+<node>
+  <interface name="dev.sp1rit.Gtopia.Library">
+    <method name="ListItems">
+      <arg type="a(sss)" name="items" direction="out"/>
+    </method>
+    <method name="GetStatus">
+      <arg type="s" name="uuid" direction="in"/>
+      <arg type="as" name="status" direction="out"/>
+    </method>
+    <method name="Launch">
+      <arg type="s" name="uuid" direction="in"/>
+    </method>
+    <method name="Close">
+      <arg type="s" name="uuid" direction="in"/>
+    </method>
+    <method name="Kill">
+      <arg type="s" name="uuid" direction="in"/>
+    </method>
+    <signal name="GameStarted">
+      <arg type="s" name="uuid"/>
+      <arg type="s" name="name"/>
+    </signal>
+    <signal name="GameStopped">
+      <arg type="s" name="uuid"/>
+      <arg type="s" name="name"/>
+    </signal>
+  </interface>
+</node>
+"#;
+
+pub fn status_name(status: &LibraryItemStatus) -> &'static str {
+	match status {
+		LibraryItemStatus::Running(_pid) => "running",
+		LibraryItemStatus::Closing => "closing",
+		LibraryItemStatus::Updatable => "updatable",
+		LibraryItemStatus::Updating => "updating",
+		LibraryItemStatus::Installed => "installed"
+	}
+}
+
+#[derive(Debug, Clone)]
+struct DBusItem {
+	name: String,
+	provider: String,
+	stati: Vec<LibraryItemStatus>
+}
+
+impl DBusItem {
+	fn new(name: &str, provider: &str, stati: &[LibraryItemStatus]) -> Self {
+		Self {
+			name: name.to_owned(),
+			provider: provider.to_owned(),
+			stati: stati.to_vec()
+		}
+	}
+}
+
+/// Mirror of the library exported on the session bus, so that other
+/// tools (status bars, stream overlays, ...) can see what is running.
+#[derive(Debug, Default)]
+pub struct UtopiaDBus {
+	registration: RefCell<Option<(gio::DBusConnection, String, gio::RegistrationId)>>,
+	sender: OnceCell<futures::channel::mpsc::Sender<UtopiaRequest>>,
+	items: RefCell<HashMap<String, DBusItem>>
+}
+
+impl UtopiaDBus {
+	pub fn set_sender(&self, sender: futures::channel::mpsc::Sender<UtopiaRequest>) {
+		self.sender.set(sender).expect("Failed setting up UtopiaDBus");
+	}
+
+	pub fn register(self: &Rc<Self>, connection: &gio::DBusConnection, object_path: &str) -> Result<(), glib::Error> {
+		let node = gio::DBusNodeInfo::for_xml(INTROSPECTION)?;
+		let interface = node
+			.lookup_interface(INTERFACE_NAME)
+			.expect("Library interface missing from introspection data");
+
+		let service = Rc::downgrade(self);
+		let id = connection.register_object(
+			object_path,
+			&interface,
+			move |_, _, _, _, method, params, invocation| match service.upgrade() {
+				Some(service) => service.method_call(method, params, invocation),
+				None => invocation.return_dbus_error("org.freedesktop.DBus.Error.Failed", "Service is shutting down")
+			},
+			// GDBus answers requests for properties missing from the
+			// introspection data with InvalidArgs, this is never reached
+			|_, _, _, _, property| {
+				eprintln!("Library interface has no property {}", property);
+				().to_variant()
+			},
+			|_, _, _, _, _, _| false
+		)?;
+		self.registration
+			.replace(Some((connection.clone(), object_path.to_owned(), id)));
+		Ok(())
+	}
+
+	pub fn unregister(&self, connection: &gio::DBusConnection) {
+		if let Some((_, _, id)) = self.registration.take() {
+			if let Err(e) = connection.unregister_object(id) {
+				eprintln!("Failed unregistering library interface: {}", e);
+			}
+		}
+	}
+
+	fn method_call(&self, method: &str, params: glib::Variant, invocation: gio::DBusMethodInvocation) {
+		match method {
+			"ListItems" => {
+				let items: Vec<(String, String, String)> = self
+					.items
+					.borrow()
+					.iter()
+					.map(|(uuid, item)| (uuid.clone(), item.name.clone(), item.provider.clone()))
+					.collect();
+				invocation.return_value(Some(&(items,).to_variant()));
+			},
+			"GetStatus" => {
+				let (uuid,) = params.get::<(String,)>().unwrap_or_default();
+				match self.items.borrow().get(&uuid) {
+					Some(item) => {
+						let stati: Vec<&str> = item.stati.iter().map(status_name).collect();
+						invocation.return_value(Some(&(stati,).to_variant()));
+					},
+					None => invocation.return_dbus_error(
+						"org.freedesktop.DBus.Error.InvalidArgs",
+						&format!("No library item with uuid {}", uuid)
+					)
+				}
+			},
+			"Launch" | "Close" | "Kill" => {
+				let (uuid,) = params.get::<(String,)>().unwrap_or_default();
+				if !self.items.borrow().contains_key(&uuid) {
+					return invocation.return_dbus_error(
+						"org.freedesktop.DBus.Error.InvalidArgs",
+						&format!("No library item with uuid {}", uuid)
+					);
+				}
+				if method != "Launch" {
+					crate::notifications::expect_exit(&uuid);
+				}
+				let request = match method {
+					"Launch" => UtopiaRequest::TriggerLaunch(uuid.clone()),
+					"Close" => UtopiaRequest::TriggerClose(LibraryItemProviderQuitActions::ActiveProvider(uuid.clone())),
+					_ => UtopiaRequest::TriggerKill(LibraryItemProviderQuitActions::ActiveProvider(uuid.clone()))
+				};
+				match self.sender.get().map(|sender| sender.clone().try_send(request)) {
+					Some(Ok(())) => invocation.return_value(None),
+					Some(Err(e)) => invocation.return_dbus_error(
+						"org.freedesktop.DBus.Error.Failed",
+						&format!("Error forwarding {} for {}: {}", method, uuid, e)
+					),
+					None => invocation.return_dbus_error(
+						"org.freedesktop.DBus.Error.Failed",
+						"Not connected to the µtopia daemon yet"
+					)
+				}
+			},
+			_ => invocation.return_dbus_error(
+				"org.freedesktop.DBus.Error.UnknownMethod",
+				&format!("Unknown method {}", method)
+			)
+		}
+	}
+
+	fn emit(&self, signal: &str, uuid: &str, name: &str) {
+		if let Some((connection, path, _)) = self.registration.borrow().as_ref() {
+			if let Err(e) = connection.emit_signal(None, path, INTERFACE_NAME, signal, Some(&(uuid, name).to_variant())) {
+				eprintln!("Failed emitting {} for {}: {}", signal, uuid, e);
+			}
+		}
+	}
+
+	fn update_item(&self, item: &LibraryItemFrontend) {
+		let was_running = self
+			.items
+			.borrow()
+			.get(&item.uuid)
+			.map(|old| is_running(&old.stati))
+			.unwrap_or(false);
+		self.items.borrow_mut().insert(
+			item.uuid.clone(),
+			DBusItem::new(&item.name, &item.active_provider.uuid, &item.active_provider.stati)
+		);
+		self.emit_transition(&item.uuid, &item.name, was_running, is_running(&item.active_provider.stati));
+	}
+
+	fn emit_transition(&self, uuid: &str, name: &str, was_running: bool, running: bool) {
+		match (was_running, running) {
+			(false, true) => self.emit("GameStarted", uuid, name),
+			(true, false) => self.emit("GameStopped", uuid, name),
+			_ => {}
+		}
+	}
+
+	/// Replaces all exported items, games that are gone or stopped
+	/// meanwhile are reported as stopped.
+	fn replace_items(&self, library: &[LibraryItemFrontendDetails]) {
+		let old = self.items.replace(
+			library
+				.iter()
+				.map(|item| {
+					let provider = &item.active_provider;
+					(item.uuid.clone(), DBusItem::new(&item.name, &provider.uuid, &provider.stati))
+				})
+				.collect()
+		);
+		for (uuid, item) in &old {
+			if !self.items.borrow().contains_key(uuid) {
+				self.emit_transition(uuid, &item.name, is_running(&item.stati), false);
+			}
+		}
+		for item in library {
+			self.emit_transition(
+				&item.uuid,
+				&item.name,
+				old.get(&item.uuid).map_or(false, |old| is_running(&old.stati)),
+				is_running(&item.active_provider.stati)
+			);
+		}
+	}
+
+	/// Keeps the exported state in sync with messages from the daemon.
+	/// Has to be called before the message is handed to the UI.
+	pub fn observe(&self, msg: &UtopiaMessage) {
+		match msg {
+			UtopiaMessage::RefreshGameLibrary(library) => self.replace_items(library),
+			UtopiaMessage::UpdateGame(item) => self.update_item(item),
+			_ => {}
+		}
+	}
+}
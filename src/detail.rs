@@ -1,8 +1,19 @@
 use gtk::{gio, glib, prelude::*, subclass::prelude::*, CompositeTemplate};
-use crate::uev::UtopiaRequest;
+use crate::{artwork::{AnimatedTexture, Palette, ScaledTexture},
+            integrations::IntegrationLayout,
+            store::{ItemStore, UtopiaItem},
+            uev::UtopiaRequest};
+
+const COVER_HEIGHT: i32 = 360;
+const HERO_SIZE: (i32, i32) = (360, 160);
+const LOGO_SIZE: (i32, i32) = (280, 96);
+const SCREENSHOT_SIZE: (i32, i32) = (320, 180);
+/// the combo draws textures at the size of large icons
+const PROVIDER_ICON_SIZE: i32 = 32;
 
 mod imp {
 	use gtk::{Box, Button, ComboBox, Label, Picture};
+	use libadwaita::Carousel;
 
 	use super::*;
 
@@ -12,19 +23,34 @@ mod imp {
 		pub running: std::rc::Rc<std::cell::Cell<bool>>,
 		pub current_uuid: std::rc::Rc<std::cell::RefCell<Option<String>>>,
 		pub current_module: std::rc::Rc<std::cell::RefCell<Option<String>>>,
+		pub item: std::cell::RefCell<Option<UtopiaItem>>,
+		pub changed_handler: std::cell::RefCell<Option<glib::SignalHandlerId>>,
+		pub store: once_cell::unsync::OnceCell<std::rc::Rc<ItemStore>>,
+		pub animation: std::rc::Rc<std::cell::RefCell<Option<AnimatedTexture>>>,
 		pub sender: once_cell::unsync::OnceCell<futures::channel::mpsc::Sender<crate::uev::UtopiaRequest>>,
 
 		pub actions: gio::SimpleActionGroup,
 		pub kill_action: once_cell::unsync::OnceCell<gio::SimpleAction>,
+		pub palette: once_cell::unsync::OnceCell<gtk::CssProvider>,
+		pub file_chooser: std::cell::RefCell<Option<gtk::FileChooserNative>>,
+		pub layout: IntegrationLayout,
 
+		#[template_child]
+		pub hero: TemplateChild<Picture>,
 		#[template_child]
 		pub cover: TemplateChild<Picture>,
 		#[template_child]
+		pub logo: TemplateChild<Picture>,
+		#[template_child]
 		pub name: TemplateChild<Label>,
 		#[template_child]
 		pub uuid: TemplateChild<Label>,
 		#[template_child]
 		pub dinfos: TemplateChild<ComboBox>,
+		#[template_child]
+		pub screenshots: TemplateChild<Box>,
+		#[template_child]
+		pub carousel: TemplateChild<Carousel>,
 
 		#[template_child]
 		pub hide_btn: TemplateChild<Button>,
@@ -53,6 +79,35 @@ mod imp {
 			obj.setup_actions();
 			self.parent_constructed(obj);
 			obj.setup_triggers();
+			self.cover.set_size_request((2 * COVER_HEIGHT) / 3, COVER_HEIGHT);
+			// the provider only applies to the widgets it's added to
+			let palette = gtk::CssProvider::new();
+			obj.style_context()
+				.add_provider(&palette, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+			self.primary_btn
+				.style_context()
+				.add_provider(&palette, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+			self.palette.set(palette).unwrap();
+			crate::artwork::overrides::connect_changed(glib::clone!(@weak obj => move |uuid| {
+				let selected = imp::UtopiaDetail::from_instance(&obj).current_uuid.borrow().as_deref() == Some(uuid);
+				if selected {
+					obj.load_cover();
+				}
+			}));
+			self.layout.connect_changed(glib::clone!(@weak obj => move || obj.refresh()));
+			// the cover and provider icons are rendered for the scale
+			obj.connect_scale_factor_notify(|detail| detail.refresh());
+			// don't animate while off-screen
+			obj.connect_map(|detail| {
+				if let Some(animation) = imp::UtopiaDetail::from_instance(detail).animation.borrow().as_ref() {
+					animation.play();
+				}
+			});
+			obj.connect_unmap(|detail| {
+				if let Some(animation) = imp::UtopiaDetail::from_instance(detail).animation.borrow().as_ref() {
+					animation.pause();
+				}
+			});
 		}
 	}
 
@@ -86,7 +141,10 @@ impl UtopiaDetail {
 				if let Some(uuid) = self_.current_uuid.borrow().as_ref() {
 					let request = match self_.running.get() {
 						false => UtopiaRequest::TriggerLaunch(uuid.into()),
-						true => UtopiaRequest::TriggerClose(utopia_common::library::LibraryItemProviderQuitActions::ActiveProvider(uuid.into()))
+						true => {
+							crate::notifications::expect_exit(uuid);
+							UtopiaRequest::TriggerClose(utopia_common::library::LibraryItemProviderQuitActions::ActiveProvider(uuid.into()))
+						}
 					};
 					if let Err(e) = self_.sender.clone().get_mut().unwrap().try_send(request) {
 						eprintln!("Error requesting to close {}: {}", uuid, e);
@@ -126,7 +184,10 @@ impl UtopiaDetail {
 			if let Some(uuid) = self_.current_uuid.borrow().as_ref() {
 				if let Some(current_module) = self_.current_module.borrow().as_ref() {
 					if let Err(e) = self_.sender.clone().get_mut().unwrap().try_send(
-						UtopiaRequest::TriggerPreferenceDiag(current_module.into(), uuid.into())
+						UtopiaRequest::TriggerPreferenceDiag(
+							current_module.into(),
+							utopia_common::library::preferences::DiagType::Item(uuid.into())
+						)
 					) {
 						eprintln!("Error trying to request preference diag from {} for {}: {}", uuid, current_module, e);
 					}
@@ -134,12 +195,52 @@ impl UtopiaDetail {
 			}
 		}));
 
+		let launcher = gio::SimpleAction::new("launcher", None);
+
+		launcher.connect_activate(glib::clone!(@strong self as detail => move |_, _| {
+			let self_ = imp::UtopiaDetail::from_instance(&detail);
+			if let Some(uuid) = self_.current_uuid.borrow().as_ref() {
+				let cover = self_
+					.cover
+					.paintable()
+					.and_then(|cover| cover.downcast::<ScaledTexture>().ok())
+					.and_then(|cover| cover.texture());
+				if let Err(e) = crate::launcher::create_launcher(uuid, &self_.name.label(), cover.as_ref()) {
+					eprintln!("Error creating launcher for {}: {}", uuid, e);
+				}
+			}
+		}));
+
+		let set_cover = gio::SimpleAction::new("set-cover", None);
+
+		set_cover.connect_activate(glib::clone!(@strong self as detail => move |_, _| {
+			detail.choose_cover();
+		}));
+
+		let reset_cover = gio::SimpleAction::new("reset-cover", None);
+
+		reset_cover.connect_activate(glib::clone!(@strong self as detail => move |_, _| {
+			let self_ = imp::UtopiaDetail::from_instance(&detail);
+			if let Some(uuid) = self_.current_uuid.borrow().as_ref() {
+				if let Err(e) = crate::artwork::overrides::remove(uuid) {
+					eprintln!("Error removing custom cover of {}: {}", uuid, e);
+				}
+			}
+		}));
+
+		let edit_tags = gio::SimpleAction::new("edit-tags", None);
+
+		edit_tags.connect_activate(glib::clone!(@strong self as detail => move |_, _| {
+			detail.edit_tags();
+		}));
+
 		let kill_action = gio::SimpleAction::new("kill", None);
 
 		kill_action.connect_activate(glib::clone!(@strong self as detail => move |_, _| {
 			let self_ = imp::UtopiaDetail::from_instance(&detail);
 			if let Some(uuid) = self_.current_uuid.borrow().as_ref() {
 				if self_.running.get() {
+					crate::notifications::expect_exit(uuid);
 					if let Err(e) = self_.sender.clone().get_mut().unwrap().try_send(
 						UtopiaRequest::TriggerKill(utopia_common::library::LibraryItemProviderQuitActions::ActiveProvider(uuid.into()))
 					) {
@@ -150,110 +251,393 @@ impl UtopiaDetail {
 		}));
 
 		&self_.actions.add_action(&pref);
+		&self_.actions.add_action(&launcher);
+		&self_.actions.add_action(&set_cover);
+		&self_.actions.add_action(&reset_cover);
+		&self_.actions.add_action(&edit_tags);
 		&self_.actions.add_action(&kill_action);
 
 		&self_.kill_action.set(kill_action).unwrap();
 	}
 
+	/// (Re-)renders the cover of the shown item for the current scale
+	/// factor.
+	fn load_cover(&self) {
+		let self_ = imp::UtopiaDetail::from_instance(self);
+		if let Some(animation) = self_.animation.take() {
+			animation.pause();
+		}
+		let item = self_.item.borrow().clone();
+		if let Some(item) = item {
+			let item = item.details();
+			if let Some(reset_cover) = self_
+				.actions
+				.lookup_action("reset-cover")
+				.and_then(|action| action.downcast::<gio::SimpleAction>().ok())
+			{
+				reset_cover.set_enabled(crate::artwork::overrides::get(&item.uuid).is_some());
+			}
+			let scale = self.scale_factor();
+			let cover = self_.cover.get();
+			let selected = self_.current_uuid.clone();
+			let item_uuid = item.uuid.clone();
+			crate::artwork::load_cover(
+				self,
+				&item,
+				scale * (2 * COVER_HEIGHT) / 3,
+				scale * COVER_HEIGHT,
+				glib::clone!(@weak self as detail, @weak cover, @strong selected, @strong item_uuid => move |texture| {
+					// the selection might have changed while loading, and
+					// the animation should not be replaced by its still
+					if selected.borrow().as_ref() != Some(&item_uuid) {
+						return;
+					}
+					if imp::UtopiaDetail::from_instance(&detail).animation.borrow().is_none() {
+						cover.set_paintable(Some(&ScaledTexture::new(&texture, scale)));
+					}
+					crate::artwork::load_palette(&texture, glib::clone!(@weak detail, @strong selected, @strong item_uuid => move |palette| {
+						if selected.borrow().as_ref() == Some(&item_uuid) {
+							detail.apply_palette(palette);
+						}
+					}));
+				})
+			);
+			crate::artwork::load_animation(
+				&item,
+				scale * (2 * COVER_HEIGHT) / 3,
+				scale * COVER_HEIGHT,
+				scale,
+				glib::clone!(@weak self as detail => move |animation| {
+					let self_ = imp::UtopiaDetail::from_instance(&detail);
+					if let Some(animation) = animation {
+						if self_.current_uuid.borrow().as_ref() == Some(&item_uuid) {
+							self_.cover.set_paintable(Some(&animation));
+							if detail.is_mapped() {
+								animation.play();
+							}
+							self_.animation.replace(Some(animation));
+						}
+					}
+				})
+			);
+		}
+		self.load_extra_artwork();
+	}
+
+	/// Lets the user pick an image file as cover of the shown item.
+	fn choose_cover(&self) {
+		let self_ = imp::UtopiaDetail::from_instance(self);
+		let window = self.root().and_then(|root| root.downcast::<gtk::Window>().ok());
+		let chooser = gtk::FileChooserNative::new(
+			Some("Choose Cover"),
+			window.as_ref(),
+			gtk::FileChooserAction::Open,
+			Some("_Open"),
+			Some("_Cancel")
+		);
+		let filter = gtk::FileFilter::new();
+		filter.set_name(Some("Images"));
+		filter.add_pixbuf_formats();
+		chooser.add_filter(&filter);
+		chooser.set_modal(true);
+
+		chooser.connect_response(glib::clone!(@weak self as detail => move |chooser, response| {
+			let self_ = imp::UtopiaDetail::from_instance(&detail);
+			if response == gtk::ResponseType::Accept {
+				if let (Some(file), Some(uuid)) = (chooser.file(), self_.current_uuid.borrow().clone()) {
+					crate::artwork::overrides::set(&uuid, &file, glib::clone!(@strong uuid => move |res| {
+						if let Err(e) = res {
+							eprintln!("Error setting custom cover of {}: {}", uuid, e);
+						}
+					}));
+				}
+			}
+			self_.file_chooser.replace(None);
+		}));
+		chooser.show();
+		// native dialogs are not kept alive by GTK
+		self_.file_chooser.replace(Some(chooser));
+	}
+
+	/// Lets the user edit the tags of the shown item, as comma separated
+	/// list.
+	fn edit_tags(&self) {
+		let self_ = imp::UtopiaDetail::from_instance(self);
+		let uuid = match self_.current_uuid.borrow().clone() {
+			Some(uuid) => uuid,
+			None => return
+		};
+		let window = self.root().and_then(|root| root.downcast::<gtk::Window>().ok());
+		let diag = gtk::Dialog::with_buttons(
+			Some("Edit Tags"),
+			window.as_ref(),
+			gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::USE_HEADER_BAR,
+			&[("_Cancel", gtk::ResponseType::Cancel), ("_Save", gtk::ResponseType::Accept)]
+		);
+		diag.set_default_response(gtk::ResponseType::Accept);
+
+		let entry = gtk::EntryBuilder::new()
+			.text(&crate::tags::tags(&uuid).join(", "))
+			.placeholder_text("finished, co-op, favorite")
+			.activates_default(true)
+			.margin_top(12)
+			.margin_bottom(12)
+			.margin_start(12)
+			.margin_end(12)
+			.build();
+		diag.content_area().append(&entry);
+
+		diag.connect_response(glib::clone!(@weak entry => move |diag, response| {
+			if response == gtk::ResponseType::Accept {
+				let tags: Vec<String> = entry.text().split(',').map(str::to_owned).collect();
+				crate::tags::set(&uuid, &tags);
+			}
+			diag.close();
+		}));
+		diag.show();
+	}
+
+	/// Tints the pane and its primary button with the colors of the
+	/// cover. Text is kept black or white, whichever contrasts more.
+	fn apply_palette(&self, palette: Option<Palette>) {
+		let self_ = imp::UtopiaDetail::from_instance(self);
+		let css = match palette {
+			Some(palette) => {
+				let background = palette.dominant;
+				let text = background.text_color();
+				// the button has to stand out from the pane
+				let accent = if palette.accent.contrast(&background) < 1.5 {
+					background.mix(&text, 0.3)
+				} else {
+					palette.accent
+				};
+				let accent_text = accent.text_color();
+				// moving away from the text keeps it readable, the text is
+				// picked again in case the state crossed over anyway
+				let hover = accent.mix(&accent_text.text_color(), 0.1);
+				let active = accent.mix(&accent_text.text_color(), 0.2);
+				format!(
+					".dtinted {{ background-color: {}; color: {}; border-radius: 8px; }}\n\
+					 button.daccent {{ background-image: none; background-color: {}; color: {}; }}\n\
+					 button.daccent:hover {{ background-color: {}; color: {}; }}\n\
+					 button.daccent:active {{ background-color: {}; color: {}; }}\n",
+					background.to_css(),
+					text.to_css(),
+					accent.to_css(),
+					accent_text.to_css(),
+					hover.to_css(),
+					hover.text_color().to_css(),
+					active.to_css(),
+					active.text_color().to_css()
+				)
+			},
+			None => String::new()
+		};
+		self_.palette.get().unwrap().load_from_data(css.as_bytes());
+	}
+
+	/// Loads a non-cover artwork of the shown item, keeping its aspect
+	/// ratio.
+	fn load_scaled<F: FnOnce(ScaledTexture) + 'static>(
+		&self,
+		uuid: &str,
+		data: &utopia_common::library::artwork::ArtworkData,
+		(width, height): (i32, i32),
+		callback: F
+	) {
+		let self_ = imp::UtopiaDetail::from_instance(self);
+		let scale = self.scale_factor();
+		let selected = self_.current_uuid.clone();
+		let item_uuid = uuid.to_owned();
+		crate::artwork::load_artwork(
+			uuid,
+			data,
+			scale * width,
+			scale * height,
+			crate::artwork::Scaling::Fit,
+			move |texture| {
+				if let Some(texture) = texture {
+					if selected.borrow().as_ref() == Some(&item_uuid) {
+						callback(ScaledTexture::new(&texture, scale));
+					}
+				}
+			}
+		);
+	}
+
+	/// Shows the hero banner, logo and screenshots the provider
+	/// supplies. Whatever is missing stays hidden, so the pane falls
+	/// back to cover and title.
+	fn load_extra_artwork(&self) {
+		let self_ = imp::UtopiaDetail::from_instance(self);
+		self_.hero.set_visible(false);
+		self_.logo.set_visible(false);
+		self_.name.set_visible(true);
+		self_.screenshots.set_visible(false);
+		for i in (0..self_.carousel.n_pages()).rev() {
+			self_.carousel.remove(&self_.carousel.nth_page(i));
+		}
+
+		let item = match self_.item.borrow().clone() {
+			Some(item) => item,
+			None => return
+		};
+		let item = item.details();
+		for artwork in &item.details.artworks {
+			match artwork.r#type {
+				utopia_common::library::artwork::ArtworkType::Background => {
+					let hero = self_.hero.get();
+					self.load_scaled(&item.uuid, &artwork.data, HERO_SIZE, move |texture| {
+						hero.set_paintable(Some(&texture));
+						hero.set_visible(true);
+					});
+				},
+				utopia_common::library::artwork::ArtworkType::Logo => {
+					let logo = self_.logo.get();
+					let name = self_.name.get();
+					self.load_scaled(&item.uuid, &artwork.data, LOGO_SIZE, move |texture| {
+						logo.set_paintable(Some(&texture));
+						logo.set_tooltip_text(Some(&name.label()));
+						logo.set_visible(true);
+						name.set_visible(false);
+					});
+				},
+				utopia_common::library::artwork::ArtworkType::Screenshot => {
+					let picture = gtk::PictureBuilder::new()
+						.width_request(SCREENSHOT_SIZE.0)
+						.height_request(SCREENSHOT_SIZE.1)
+						.build();
+					self_.carousel.append(&picture);
+					let screenshots = self_.screenshots.get();
+					self.load_scaled(&item.uuid, &artwork.data, SCREENSHOT_SIZE, move |texture| {
+						picture.set_paintable(Some(&texture));
+						screenshots.set_visible(true);
+					});
+				},
+				_ => {}
+			}
+		}
+	}
+
 	pub fn init(
 		&self,
 		sender: futures::channel::mpsc::Sender<UtopiaRequest>,
+		store: std::rc::Rc<ItemStore>,
 		listener: glib::Receiver<crate::grid::SidebarMsg>
 	) {
 		let self_ = imp::UtopiaDetail::from_instance(self);
 		self_.sender.set(sender).expect("Failed setting up UtopiaDetail");
-		let cover = self_.cover.get();
-		let name = self_.name.get();
-		let uuid = self_.uuid.get();
-		let info = self_.dinfos.get();
-		let current_uuid = self_.current_uuid.clone();
-		let current_module = self_.current_module.clone();
-		let primary_btn = self_.primary_btn.get();
-		let kill_action = self_.kill_action.get().unwrap();
-		let running = self_.running.clone();
-		listener.attach(None, glib::clone!(@weak self as detail, @weak cover, @weak name, @weak uuid, @weak info, @weak primary_btn, @weak kill_action => @default-return glib::Continue(false), move |msg| {
-    		match msg.item {
-    			Some(item) => {
-    				if msg.action == crate::grid::SidebarMsgAction::Update && current_uuid.borrow().as_ref() != Some(item.uuid.clone()).as_ref() {
-    					println!("TRACE: non-selected item got updated");
-    					return glib::Continue(true)
-    				}
-    				for artwork in item.details.artworks {
-						match artwork.r#type {
-							utopia_common::library::artwork::ArtworkType::CaseCover => {
-								let buf = match artwork.data {
-									utopia_common::library::artwork::ArtworkData::Data(data, has_alpha, bits_per_sample, width, height, rowstride) => {
-										gtk::gdk_pixbuf::Pixbuf::from_bytes(&gtk::glib::Bytes::from(&data), gtk::gdk_pixbuf::Colorspace::Rgb, has_alpha, bits_per_sample, width, height, rowstride)
-									},
-									utopia_common::library::artwork::ArtworkData::Uri(_uri) => {
-										//unimplemented!();
-										gtk::gdk_pixbuf::Pixbuf::from_resource("/dev/sp1rit/Utopia/artwork.svg").unwrap()
-									},
-									utopia_common::library::artwork::ArtworkData::Path(path) => {
-										gtk::gdk_pixbuf::Pixbuf::from_file(path).unwrap_or(
-											gtk::gdk_pixbuf::Pixbuf::from_resource("/dev/sp1rit/Utopia/artwork.svg").unwrap()
-										)
-									}
-								};
-								let size = 360;
-								let buf = buf.scale_simple((2*size)/3, size, gtk::gdk_pixbuf::InterpType::Bilinear);
-								cover.set_pixbuf(buf.as_ref());
-							},
-							_ => {}
-						}
-					}
+		self_.store.set(store).expect("Failed setting up UtopiaDetail");
+		listener.attach(None, glib::clone!(@weak self as detail => @default-return glib::Continue(false), move |msg| {
+			match msg.uuid {
+				Some(uuid) => detail.show(&uuid),
+				None => detail.set_visible(false)
+			};
+			glib::Continue(true)
+		}));
+	}
 
-					name.set_label(&item.name);
-					uuid.set_label(&item.uuid);
-
-					current_uuid.replace(Some(item.uuid));
-    				current_module.replace(Some(item.active_provider.uuid.clone()));
-
-					info.clear();
-    				let prov = gtk::ListStore::new(&[glib::types::Type::STRING, glib::types::Type::STRING, glib::types::Type::STRING]);
-    				for (iuuid, iprov) in item.providers {
-    					prov.insert_with_values(None, &[(0, &iuuid), (1, &iprov.icon.unwrap_or(String::from(""))), (2, &iprov.name)]);
-    				}
-    				info.set_model(Some(&prov));
-    				info.set_id_column(0);
-    				let icon = gtk::CellRendererPixbufBuilder::new()
-    					.icon_size(gtk::IconSize::Large)
-    					.build();
-    				info.pack_start(&icon, false);
-    				info.add_attribute(&icon, "icon-name", 1);
-    				let text = gtk::CellRendererTextBuilder::new()
-    					.ellipsize(gtk::pango::EllipsizeMode::End)
-    					.ellipsize_set(true)
-    					.build();
-    				info.pack_start(&text, false);
-    				info.add_attribute(&text, "text", 2);
-
-    				info.set_active_id(Some(&item.active_provider.uuid));
-    				detail.set_visible(true);
-
-    				if item.active_provider.stati.iter().any(|&i| std::mem::discriminant(&i) == std::mem::discriminant(&utopia_common::library::LibraryItemStatus::Running(None))) {
-						running.set(true);
-						primary_btn.set_label("Stop");
-
-					} else {
-						running.set(false);
-						primary_btn.set_label("Launch");
-					}
-					if item.active_provider.stati.iter().any(|&i| {
-						if let utopia_common::library::LibraryItemStatus::Running(Some(_)) = i {
-							true
-						} else {
-							false
-						}
-					}) {
-						kill_action.set_enabled(true);
-					} else {
-						kill_action.set_enabled(false);
-					}
-    			},
-    			None => detail.set_visible(false)
-    		};
-    		glib::Continue(true)
-    	}));
+	/// Shows the item, following its changes until another one gets
+	/// shown.
+	fn show(&self, uuid: &str) {
+		let self_ = imp::UtopiaDetail::from_instance(self);
+		let item = match self_.store.get().unwrap().item(uuid) {
+			Some(item) => item,
+			None => return self.set_visible(false)
+		};
+		if self_.current_uuid.borrow().as_deref() != Some(uuid) {
+			if let (Some(previous), Some(handler)) = (self_.item.take(), self_.changed_handler.take()) {
+				previous.disconnect(handler);
+			}
+			let handler = item.connect_changed(glib::clone!(@weak self as detail => move |_| detail.refresh()));
+			self_.changed_handler.replace(Some(handler));
+
+			let scale = self.scale_factor();
+			self_.cover.set_paintable(Some(&ScaledTexture::new(
+				&crate::artwork::placeholder_cover(self, &item.details(), scale * (2 * COVER_HEIGHT) / 3, scale * COVER_HEIGHT),
+				scale
+			)));
+			self_.current_uuid.replace(Some(uuid.to_owned()));
+			self_.item.replace(Some(item));
+		}
+		self.refresh();
+		self.set_visible(true);
+	}
+
+	/// Updates the pane from the shown item.
+	fn refresh(&self) {
+		let self_ = imp::UtopiaDetail::from_instance(self);
+		let item = match self_.item.borrow().clone() {
+			Some(item) => item,
+			None => return
+		};
+		{
+			let item = item.details();
+			self_.name.set_label(&item.name);
+			self_.uuid.set_label(&item.uuid);
+		}
+		self.load_cover();
+
+		let item = item.details();
+		self_.current_module.replace(Some(item.active_provider.uuid.clone()));
+
+		let info = &self_.dinfos;
+		info.clear();
+		let prov = gtk::ListStore::new(&[
+			glib::types::Type::STRING,
+			glib::types::Type::STRING,
+			glib::types::Type::STRING,
+			gtk::gdk::Texture::static_type()
+		]);
+		// hidden providers stay selectable for the games they are active for
+		let layout = &self_.layout;
+		let mut providers: Vec<_> = item
+			.providers
+			.iter()
+			.filter(|(iuuid, _)| **iuuid == item.active_provider.uuid || !layout.is_hidden(iuuid))
+			.collect();
+		providers.sort_by_cached_key(|(iuuid, iprov)| {
+			(layout.position(iuuid), glib::utf8_collate_key(&layout.label(iuuid, &iprov.name), -1))
+		});
+		for (iuuid, iprov) in providers {
+			let iter = prov.insert_with_values(None, &[(0, iuuid), (2, &layout.label(iuuid, &iprov.name))]);
+			crate::artwork::load_provider_icon(
+				self.upcast_ref(),
+				iuuid,
+				&iprov.name,
+				iprov.icon.as_deref(),
+				self.scale_factor() * PROVIDER_ICON_SIZE,
+				glib::clone!(@weak prov => move |icon| match icon {
+					crate::artwork::ProviderIcon::Named(name) => prov.set(&iter, &[(1, &name)]),
+					crate::artwork::ProviderIcon::Texture(texture) => prov.set(&iter, &[(3, &texture)])
+				})
+			);
+		}
+		info.set_model(Some(&prov));
+		info.set_id_column(0);
+		let icon = gtk::CellRendererPixbufBuilder::new()
+			.icon_size(gtk::IconSize::Large)
+			.build();
+		info.pack_start(&icon, false);
+		info.add_attribute(&icon, "icon-name", 1);
+		info.add_attribute(&icon, "texture", 3);
+		let text = gtk::CellRendererTextBuilder::new()
+			.ellipsize(gtk::pango::EllipsizeMode::End)
+			.ellipsize_set(true)
+			.build();
+		info.pack_start(&text, false);
+		info.add_attribute(&text, "text", 2);
+
+		info.set_active_id(Some(&item.active_provider.uuid));
+
+		if item.active_provider.stati.iter().any(|&i| std::mem::discriminant(&i) == std::mem::discriminant(&utopia_common::library::LibraryItemStatus::Running(None))) {
+			self_.running.set(true);
+			self_.primary_btn.set_label("Stop");
+		} else {
+			self_.running.set(false);
+			self_.primary_btn.set_label("Launch");
+		}
+		let killable = item.active_provider.stati.iter().any(|&i| matches!(i, utopia_common::library::LibraryItemStatus::Running(Some(_))));
+		self_.kill_action.get().unwrap().set_enabled(killable);
 	}
 }
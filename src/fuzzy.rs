@@ -0,0 +1,136 @@
+/// Folds the common latin diacritics, so that "Pokémon" matches
+/// "pokemon".
+fn fold(c: char) -> char {
+	match c {
+		'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+		'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+		'ď' | 'đ' => 'd',
+		'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+		'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+		'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+		'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+		'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+		'ŕ' | 'ŗ' | 'ř' => 'r',
+		'ś' | 'ŝ' | 'ş' | 'š' => 's',
+		'ţ' | 'ť' => 't',
+		'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+		'ý' | 'ÿ' => 'y',
+		'ź' | 'ż' | 'ž' => 'z',
+		c => c
+	}
+}
+
+/// Lowercases and folds the text, turning everything but letters and
+/// digits into single spaces.
+pub fn normalize(text: &str) -> String {
+	let mut normalized = String::with_capacity(text.len());
+	for c in text.chars().flat_map(char::to_lowercase).map(fold) {
+		if c.is_alphanumeric() {
+			normalized.push(c);
+		} else if !normalized.is_empty() && !normalized.ends_with(' ') {
+			normalized.push(' ');
+		}
+	}
+	let len = normalized.trim_end().len();
+	normalized.truncate(len);
+	normalized
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+	let mut row: Vec<usize> = (0..=b.len()).collect();
+	for (i, ca) in a.iter().enumerate() {
+		let mut diagonal = row[0];
+		row[0] = i + 1;
+		for (j, cb) in b.iter().enumerate() {
+			let substitution = diagonal + (ca != cb) as usize;
+			diagonal = row[j + 1];
+			row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+		}
+	}
+	row[b.len()]
+}
+
+/// How alike two strings are, from 0 to 1. Both have to be
+/// [normalized](normalize) already.
+pub fn similarity(a: &str, b: &str) -> f64 {
+	if a.is_empty() || b.is_empty() {
+		return 0.0;
+	}
+	if a == b {
+		return 1.0;
+	}
+	let (ca, cb): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+	let chars = 1.0 - levenshtein(&ca, &cb) as f64 / ca.len().max(cb.len()) as f64;
+
+	// words catch reordering and extra words, like "cover" in file
+	// names
+	let (wa, wb): (Vec<&str>, Vec<&str>) = (a.split(' ').collect(), b.split(' ').collect());
+	let common = wa.iter().filter(|word| wb.contains(word)).count();
+	let words = 2.0 * common as f64 / (wa.len() + wb.len()) as f64;
+
+	chars.max(words * 0.95)
+}
+
+/// Length in chars of the shortest window of `text` that contains the
+/// chars of `query` in order, if there is one.
+fn subsequence_span(query: &[char], text: &[char]) -> Option<usize> {
+	let mut best = None;
+	for start in (0..text.len()).filter(|&i| text[i] == query[0]) {
+		let mut matched = 1;
+		let mut end = start;
+		for (i, c) in text.iter().enumerate().skip(start + 1) {
+			if matched == query.len() {
+				break;
+			}
+			if *c == query[matched] {
+				matched += 1;
+				end = i;
+			}
+		}
+		if matched == query.len() {
+			let span = end - start + 1;
+			best = Some(best.map_or(span, |best: usize| best.min(span)));
+		}
+	}
+	best
+}
+
+/// How well `query` matches `text`, from 0 to 1, or `None` if it
+/// doesn't. Both have to be [normalized](normalize) already. Prefixes
+/// rank above word starts, word starts above substrings, and those
+/// above initials ("botw"), separate words and scattered chars.
+pub fn match_score(query: &str, text: &str) -> Option<f64> {
+	if query.is_empty() {
+		return Some(1.0);
+	}
+	if let Some(position) = text.find(query) {
+		return Some(match position {
+			0 => 1.0,
+			position if text[..position].ends_with(' ') => 0.9,
+			_ => 0.75
+		});
+	}
+
+	let compact: Vec<char> = query.chars().filter(|c| *c != ' ').collect();
+	// one char per word, so any match covers consecutive word starts, as
+	// "botw" in "the legend of zelda breath of the wild"
+	let initials: String = text.split(' ').filter_map(|word| word.chars().next()).collect();
+	if compact.len() >= 2 {
+		match initials.find(&compact.iter().collect::<String>()) {
+			Some(0) => return Some(0.85),
+			Some(_) => return Some(0.8),
+			None => ()
+		}
+	}
+
+	let words: Vec<&str> = query.split(' ').collect();
+	if words.len() > 1 && words.iter().all(|word| text.contains(word)) {
+		return Some(0.7);
+	}
+
+	if compact.len() < 2 {
+		return None;
+	}
+	let text: Vec<char> = text.chars().collect();
+	subsequence_span(&compact, &text).map(|span| 0.6 * compact.len() as f64 / span as f64)
+}
@@ -1,14 +1,26 @@
-use gtk::{gio, glib, prelude::*, subclass::prelude::*, CompositeTemplate};
+use gtk::{gdk, gio, glib, prelude::*, subclass::prelude::*, CompositeTemplate};
+use libadwaita::subclass::prelude::*;
+
+use crate::store::UtopiaItem;
+use crate::artwork::{AnimatedTexture, ScaledTexture};
+
+const COVER_HEIGHT: i32 = 300;
 
 mod imp {
-	use gtk::{FlowBoxChild, Frame, Label, Overlay, Picture};
+	use gtk::{Frame, Label, Overlay, Picture};
+	use libadwaita::Bin;
 
 	use super::*;
 
 	#[derive(Debug, Default, CompositeTemplate)]
 	#[template(resource = "/dev/sp1rit/Utopia/ui/card.ui")]
 	pub struct UtopiaCard {
-		pub utopia: std::cell::RefCell<Option<utopia_common::library::LibraryItemFrontendDetails>>,
+		pub item: std::cell::RefCell<Option<UtopiaItem>>,
+		pub changed_handler: std::cell::RefCell<Option<glib::SignalHandlerId>>,
+		pub animation: std::cell::RefCell<Option<AnimatedTexture>>,
+		pub animation_requested: std::cell::Cell<bool>,
+		pub hovered: std::cell::Cell<bool>,
+		pub focused: std::cell::Cell<bool>,
 
 		#[template_child]
 		pub frame: TemplateChild<Frame>,
@@ -25,7 +37,7 @@ mod imp {
 
 	#[glib::object_subclass]
 	impl ObjectSubclass for UtopiaCard {
-		type ParentType = FlowBoxChild;
+		type ParentType = Bin;
 		type Type = super::UtopiaCard;
 
 		const NAME: &'static str = "UtopiaCard";
@@ -42,16 +54,66 @@ mod imp {
 	impl ObjectImpl for UtopiaCard {
 		fn constructed(&self, obj: &Self::Type) {
 			self.parent_constructed(obj);
+			self.coverimg
+				.set_size_request((2 * COVER_HEIGHT) / 3, COVER_HEIGHT);
+			obj.connect_scale_factor_notify(|card| card.load_cover());
+
+			let motion = gtk::EventControllerMotion::new();
+			motion.connect_enter(glib::clone!(@weak obj => move |_, _, _| {
+				imp::UtopiaCard::from_instance(&obj).hovered.set(true);
+				obj.update_animation();
+			}));
+			motion.connect_leave(glib::clone!(@weak obj => move |_| {
+				imp::UtopiaCard::from_instance(&obj).hovered.set(false);
+				obj.update_animation();
+			}));
+			obj.add_controller(&motion);
+
+			// the grid view focuses the cell around the card, and keeps
+			// the card in the same cell while recycling it
+			let focus = gtk::EventControllerFocus::new();
+			focus.connect_enter(glib::clone!(@weak obj => move |_| {
+				imp::UtopiaCard::from_instance(&obj).focused.set(true);
+				obj.update_animation();
+			}));
+			focus.connect_leave(glib::clone!(@weak obj => move |_| {
+				imp::UtopiaCard::from_instance(&obj).focused.set(false);
+				obj.update_animation();
+			}));
+			obj.connect_realize(move |card| {
+				if let Some(cell) = card.parent() {
+					if focus.widget().is_none() {
+						cell.add_controller(&focus);
+					}
+				}
+			});
+
+			obj.connect_unmap(|card| card.update_animation());
+
+			// dropping an image sets it as cover
+			let drop = gtk::DropTarget::new(gio::File::static_type(), gdk::DragAction::COPY);
+			drop.connect_drop(glib::clone!(@weak obj => @default-return false, move |_, value, _, _| {
+				match value.get::<gio::File>() {
+					Ok(file) => {
+						obj.set_custom_cover(&file);
+						true
+					},
+					Err(_) => false
+				}
+			}));
+			obj.add_controller(&drop);
 		}
 	}
 
 	impl WidgetImpl for UtopiaCard {}
-	impl FlowBoxChildImpl for UtopiaCard {}
+	impl BinImpl for UtopiaCard {}
 }
 
 glib::wrapper! {
+	/// Renders an item of the library grid. Cards are recycled by the
+	/// grid view, so they get bound to different items over time.
 	pub struct UtopiaCard(ObjectSubclass<imp::UtopiaCard>)
-		@extends gtk::Widget, gtk::FlowBoxChild,
+		@extends gtk::Widget, libadwaita::Bin,
 		@implements gio::ActionMap, gio::ActionGroup;
 }
 
@@ -60,112 +122,113 @@ impl UtopiaCard {
 		glib::Object::new(&[]).expect("Failed to create UtopiaCard")
 	}
 
-	pub fn init(&self, item: utopia_common::library::LibraryItemFrontendDetails) {
-		self.set_widget_name(&item.uuid);
+	pub fn bind(&self, item: &UtopiaItem) {
+		self.unbind();
 		let self_ = imp::UtopiaCard::from_instance(self);
-		self_.utopia.replace(Some(item.clone()));
-		self_.title.set_label(&item.name);
-		for status in item.active_provider.stati {
-			self_.status.set_label(match status {
-				utopia_common::library::LibraryItemStatus::Running(_pid) => "Running",
-				utopia_common::library::LibraryItemStatus::Closing => "Closing",
-				utopia_common::library::LibraryItemStatus::Updatable => "Update available",
-				utopia_common::library::LibraryItemStatus::Updating => "Updating",
-				utopia_common::library::LibraryItemStatus::Installed => "Installed"
-			});
-		}
-		let size = 300;
-		if item.details.artworks.len() == 0 {
-			self_.coverimg.set_pixbuf(Some(
-				&gtk::gdk_pixbuf::Pixbuf::from_resource_at_scale(
-					"/dev/sp1rit/Utopia/artwork.svg",
-					(2 * size) / 3,
-					size,
-					true
-				)
-				.unwrap()
-			));
-		}
-		for artwork in item.details.artworks {
-			match artwork.r#type {
-				utopia_common::library::artwork::ArtworkType::CaseCover => {
-					let buf = match artwork.data {
-						utopia_common::library::artwork::ArtworkData::Data(
-							data,
-							has_alpha,
-							bits_per_sample,
-							width,
-							height,
-							rowstride
-						) => gtk::gdk_pixbuf::Pixbuf::from_bytes(
-							&gtk::glib::Bytes::from(&data),
-							gtk::gdk_pixbuf::Colorspace::Rgb,
-							has_alpha,
-							bits_per_sample,
-							width,
-							height,
-							rowstride
-						),
-						utopia_common::library::artwork::ArtworkData::Uri(_uri) => {
-							//unimplemented!();
-							gtk::gdk_pixbuf::Pixbuf::from_resource("/dev/sp1rit/Utopia/artwork.svg").unwrap()
-						},
-						utopia_common::library::artwork::ArtworkData::Path(path) => gtk::gdk_pixbuf::Pixbuf::from_file(
-							path
-						)
-						.unwrap_or(gtk::gdk_pixbuf::Pixbuf::from_resource("/dev/sp1rit/Utopia/artwork.svg").unwrap())
-					};
-					let buf = buf.scale_simple((2 * size) / 3, size, gtk::gdk_pixbuf::InterpType::Bilinear);
-					self_.coverimg.set_pixbuf(buf.as_ref());
-				},
-				_ => {}
+		let handler = item.connect_changed(glib::clone!(@weak self as card => move |_| card.refresh()));
+		self_.changed_handler.replace(Some(handler));
+		self_.item.replace(Some(item.clone()));
+		// don't show the previous item while the cover loads
+		self_.coverimg.set_paintable(None::<&gdk::Paintable>);
+		self.refresh();
+	}
+
+	pub fn unbind(&self) {
+		let self_ = imp::UtopiaCard::from_instance(self);
+		if let Some(item) = self_.item.take() {
+			if let Some(handler) = self_.changed_handler.take() {
+				item.disconnect(handler);
 			}
 		}
+		if let Some(animation) = self_.animation.take() {
+			animation.pause();
+		}
+		self_.animation_requested.set(false);
 	}
 
-	pub fn utopia(&self) -> std::cell::Ref<utopia_common::library::LibraryItemFrontendDetails> {
+	pub fn item(&self) -> Option<UtopiaItem> {
 		let self_ = imp::UtopiaCard::from_instance(self);
-		let a = self_.utopia.borrow();
-		let b: std::cell::Ref<utopia_common::library::LibraryItemFrontendDetails> = std::cell::Ref::map(a, |inner| {
-			let details = inner.as_ref().unwrap();
-			details
-		});
-		b
-		//a.as_ref().unwrap()
+		self_.item.borrow().clone()
 	}
 
-	pub fn provider(&self, provider: &glib::GString) -> bool {
-		for (iprovider, _) in &self.utopia().providers {
-			if provider == iprovider {
-				// TODO: set active provider to provider
-				return true;
-			}
+	fn refresh(&self) {
+		let self_ = imp::UtopiaCard::from_instance(self);
+		if let Some(item) = self.item() {
+			self_.title.set_label(&item.name());
+			self_.status.set_label(item.status().unwrap_or(""));
+			self.load_cover();
 		}
-		false
 	}
 
-	pub fn name(&self) -> String {
-		self.utopia().name.clone()
+	/// (Re-)renders the cover for the current scale factor.
+	pub fn load_cover(&self) {
+		let self_ = imp::UtopiaCard::from_instance(self);
+		if let Some(animation) = self_.animation.take() {
+			animation.pause();
+		}
+		self_.animation_requested.set(false);
+		if let Some(item) = self.item() {
+			let scale = self.scale_factor();
+			let coverimg = self_.coverimg.get();
+			crate::artwork::load_cover(
+				self,
+				&item.details(),
+				scale * (2 * COVER_HEIGHT) / 3,
+				scale * COVER_HEIGHT,
+				glib::clone!(@weak self as card, @weak coverimg, @strong item => move |texture| {
+					// the card might have been recycled while loading
+					if card.item().as_ref() == Some(&item) {
+						coverimg.set_paintable(Some(&ScaledTexture::new(&texture, scale)))
+					}
+				})
+			);
+		}
 	}
 
-	pub fn update(&self, item: utopia_common::library::LibraryItemFrontend) {
+	fn set_custom_cover(&self, file: &gio::File) {
+		if let Some(uuid) = self.item().map(|item| item.uuid()) {
+			crate::artwork::overrides::set(&uuid, file, glib::clone!(@strong uuid => move |res| {
+				if let Err(e) = res {
+					eprintln!("Error setting custom cover of {}: {}", uuid, e);
+				}
+			}));
+		}
+	}
+
+	/// Animated covers only play while the card is hovered or focused.
+	/// They are decoded the first time that happens.
+	fn update_animation(&self) {
 		let self_ = imp::UtopiaCard::from_instance(self);
-		self_.title.set_label(&item.name);
-		for status in item.active_provider.stati.clone() {
-			self_.status.set_label(match status {
-				utopia_common::library::LibraryItemStatus::Running(_pid) => "Running",
-				utopia_common::library::LibraryItemStatus::Closing => "Closing",
-				utopia_common::library::LibraryItemStatus::Updatable => "Update available",
-				utopia_common::library::LibraryItemStatus::Updating => "Updating",
-				utopia_common::library::LibraryItemStatus::Installed => "Installed"
-			});
+		let active = self.is_mapped() && (self_.hovered.get() || self_.focused.get());
+		if let Some(animation) = self_.animation.borrow().as_ref() {
+			if active {
+				self_.coverimg.set_paintable(Some(animation));
+				animation.play();
+			} else {
+				animation.pause();
+			}
+			return;
 		}
-		let mut utopia = self_.utopia.borrow_mut();
-		if let Some(details) = utopia.as_mut() {
-			details.name = item.name;
-			details.kind = item.kind;
-			details.active_provider = item.active_provider;
-			details.providers = item.providers;
+
+		if active && !self_.animation_requested.get() {
+			if let Some(item) = self.item() {
+				self_.animation_requested.set(true);
+				let scale = self.scale_factor();
+				crate::artwork::load_animation(
+					&item.details(),
+					scale * (2 * COVER_HEIGHT) / 3,
+					scale * COVER_HEIGHT,
+					scale,
+					glib::clone!(@weak self as card, @strong item => move |animation| {
+						if let Some(animation) = animation {
+							if card.item().as_ref() == Some(&item) {
+								imp::UtopiaCard::from_instance(&card).animation.replace(Some(animation));
+								card.update_animation();
+							}
+						}
+					})
+				);
+			}
 		}
 	}
 }
@@ -0,0 +1,97 @@
+use gtk::prelude::*;
+
+use crate::{query::Status, store::UtopiaItem};
+
+/// Stati offered in the filter bar, in this order.
+pub const STATI: [Status; 4] = [Status::Installed, Status::Running, Status::Updatable, Status::Updating];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chip {
+	Status(Status),
+	Kind(String)
+}
+
+impl Chip {
+	fn label(&self) -> String {
+		match self {
+			Self::Status(Status::Installed) => String::from("Installed"),
+			Self::Status(Status::Running) => String::from("Running"),
+			Self::Status(Status::Closing) => String::from("Closing"),
+			Self::Status(Status::Updatable) => String::from("Updatable"),
+			Self::Status(Status::Updating) => String::from("Updating"),
+			Self::Kind(kind) => kind.clone()
+		}
+	}
+
+	pub fn matches(&self, item: &UtopiaItem) -> bool {
+		match self {
+			Self::Status(status) => status.applies_to(item),
+			Self::Kind(kind) => &item.kind_name() == kind
+		}
+	}
+}
+
+/// Chips toggled on. Chips of the same group widen the filter, while both
+/// groups together narrow it.
+#[derive(Debug, Default)]
+pub struct Selection(Vec<Chip>);
+
+impl Selection {
+	pub fn set(&mut self, chip: &Chip, active: bool) {
+		self.0.retain(|other| other != chip);
+		if active {
+			self.0.push(chip.clone());
+		}
+	}
+
+	/// Whether any status chip, or with `false` any kind chip, is on.
+	pub fn has(&self, status: bool) -> bool {
+		self.0.iter().any(|chip| matches!(chip, Chip::Status(_)) == status)
+	}
+
+	pub fn matches(&self, item: &UtopiaItem) -> bool {
+		let group = |status: bool| {
+			let mut chips = self.0.iter().filter(|chip| matches!(chip, Chip::Status(_)) == status).peekable();
+			chips.peek().is_none() || chips.any(|chip| chip.matches(item))
+		};
+		group(true) && group(false)
+	}
+}
+
+/// A chip of the filter bar, showing how many items it would match.
+#[derive(Debug)]
+pub struct ChipButton {
+	pub chip: Chip,
+	pub button: gtk::ToggleButton,
+	count: gtk::Label
+}
+
+impl ChipButton {
+	pub fn new(chip: Chip) -> Self {
+		let label = gtk::Label::new(Some(&chip.label()));
+		let count = gtk::Label::new(None);
+		count.add_css_class("gcount");
+		let content = gtk::BoxBuilder::new()
+			.orientation(gtk::Orientation::Horizontal)
+			.spacing(6)
+			.build();
+		content.append(&label);
+		content.append(&count);
+		let button = gtk::ToggleButtonBuilder::new()
+			.child(&content)
+			.css_classes(vec![String::from("gchip")])
+			.build();
+		Self {
+			chip,
+			button,
+			count
+		}
+	}
+
+	/// Shows the count, chips that would empty the library are only
+	/// clickable while toggled on.
+	pub fn set_count(&self, count: usize) {
+		self.count.set_label(&count.to_string());
+		self.button.set_sensitive(count > 0 || self.button.is_active());
+	}
+}
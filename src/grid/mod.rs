@@ -1,36 +1,33 @@
 pub mod card;
+mod chips;
+mod search;
+mod sort;
 
 use gtk::{gio, glib, prelude::*, subclass::prelude::*, CompositeTemplate};
 use libadwaita::subclass::prelude::*;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum SidebarMsgAction {
-	Trigger,
-	Update
-}
+pub use sort::SortOrder;
+use crate::{query::{Key, Query},
+            store::{ItemChanges, ItemStore, UtopiaItem}};
+
+/// Tells the detail pane which item got selected.
 #[derive(Debug, Clone)]
 pub struct SidebarMsg {
-	pub item: Option<utopia_common::library::LibraryItemFrontendDetails>,
-	pub active_module: Option<glib::GString>,
-	pub action: SidebarMsgAction
+	pub uuid: Option<String>,
+	pub active_modules: Vec<String>
 }
 impl SidebarMsg {
-	pub fn new(
-		item: Option<utopia_common::library::LibraryItemFrontendDetails>,
-		active_module: Option<glib::GString>,
-		action: SidebarMsgAction
-	) -> Self {
+	pub fn new(uuid: Option<String>, active_modules: Vec<String>) -> Self {
 		SidebarMsg {
-			item,
-			active_module,
-			action
+			uuid,
+			active_modules
 		}
 	}
 }
 
 mod imp {
 	use libadwaita::Bin;
-	use gtk::FlowBox;
+	use gtk::GridView;
 
 	use super::*;
 
@@ -38,13 +35,25 @@ mod imp {
 	#[template(resource = "/dev/sp1rit/Utopia/ui/grid.ui")]
 	pub struct UtopiaGrid {
 		pub sender: once_cell::unsync::OnceCell<futures::channel::mpsc::Sender<crate::uev::UtopiaRequest>>,
-		pub dsender: once_cell::unsync::OnceCell<glib::Sender<SidebarMsg>>,
 
-		pub items: std::cell::RefCell<std::collections::HashMap<String, card::UtopiaCard>>,
-		pub active_module: std::cell::RefCell<Option<glib::GString>>,
+		pub store: once_cell::unsync::OnceCell<std::rc::Rc<ItemStore>>,
+		/// integrations to show the games of, all if empty
+		pub active_modules: std::rc::Rc<std::cell::RefCell<Vec<String>>>,
+		pub query: std::rc::Rc<std::cell::RefCell<Query>>,
+		pub chips: std::rc::Rc<std::cell::RefCell<chips::Selection>>,
+		pub chip_buttons: std::cell::RefCell<Vec<chips::ChipButton>>,
+		pub counts_pending: std::cell::Cell<bool>,
+		pub sort_order: std::rc::Rc<std::cell::Cell<SortOrder>>,
+		pub settings: once_cell::unsync::OnceCell<gio::Settings>,
+
+		pub filter: once_cell::unsync::OnceCell<gtk::CustomFilter>,
+		pub sorter: once_cell::unsync::OnceCell<gtk::CustomSorter>,
+		pub selection: once_cell::unsync::OnceCell<gtk::SingleSelection>,
 
 		#[template_child]
-		pub grid: TemplateChild<FlowBox>
+		pub chip_bar: TemplateChild<gtk::Box>,
+		#[template_child]
+		pub grid: TemplateChild<GridView>
 	}
 
 	#[glib::object_subclass]
@@ -84,117 +93,340 @@ impl UtopiaGrid {
 		glib::Object::new(&[]).expect("Failed to create UtopiaGrid")
 	}
 
-	pub fn setup_trigger(&self, dsender: glib::Sender<SidebarMsg>) {
+	/// Sets up the model chain: the store holds every item, filtered by
+	/// integration and query, sorted by name and rendered by recycled
+	/// cards.
+	fn setup_model(&self, store: &ItemStore) {
 		let self_ = imp::UtopiaGrid::from_instance(self);
-		let sender = std::sync::Arc::new(std::sync::RwLock::new(self_.sender.clone()));
-		self_.grid.connect_child_activated(move |_, child| {
-			if let Err(e) = sender
-				.write()
-				.unwrap()
-				.get_mut()
-				.unwrap()
-				.try_send(crate::uev::UtopiaRequest::TriggerLaunch(child.widget_name().into()))
-			{
-				eprintln!("Error requesting {} to launch: {}", child.widget_name(), e);
-			}
+
+		let modules = self_.active_modules.clone();
+		let query = self_.query.clone();
+		let chips = self_.chips.clone();
+		let filter = gtk::CustomFilter::new(move |item| {
+			let item = item.downcast_ref::<UtopiaItem>().unwrap();
+			chips.borrow().matches(item) && matches(&modules.borrow(), &query.borrow(), item).is_some()
 		});
+		let filtered = gtk::FilterListModel::new(Some(store.model()), Some(&filter));
 
-		let module = self_.active_module.borrow().clone();
-		self_.grid.connect_selected_children_changed(move |grid| {
-			let item = match grid.selected_children().get(0) {
-				Some(child) => Some(child.downcast_ref::<card::UtopiaCard>().unwrap().utopia().clone()),
-				None => None
+		let order = self_.sort_order.clone();
+		let query = self_.query.clone();
+		let sorter = gtk::CustomSorter::new(move |a, b| {
+			let (a, b) = (a.downcast_ref::<UtopiaItem>().unwrap(), b.downcast_ref::<UtopiaItem>().unwrap());
+			let query = query.borrow();
+			// best matches first while searching
+			let by_score = if query.text.is_empty() {
+				std::cmp::Ordering::Equal
+			} else {
+				let rank = |item: &UtopiaItem| score(&query, item).unwrap_or(0.0);
+				rank(b).partial_cmp(&rank(a)).unwrap_or(std::cmp::Ordering::Equal)
 			};
-			dsender
-				.send(SidebarMsg::new(item, module.clone(), SidebarMsgAction::Trigger))
-				.unwrap()
+			by_score.then_with(|| order.get().compare(a, b)).into()
 		});
+		let sorted = gtk::SortListModel::new(Some(&filtered), Some(&sorter));
 
-		/* probably the worst sort function known to mankind */
-		self_.grid.set_sort_func(move |b, n| {
-			let bname = b.downcast_ref::<card::UtopiaCard>().unwrap().name();
-			let bchars = bname.chars();
-			let nname = n.downcast_ref::<card::UtopiaCard>().unwrap().name();
-			let mut nchars = nname.chars();
-			for r#char in bchars {
-				let bint = r#char.to_ascii_uppercase() as u32;
-				let nint = match nchars.next() {
-					Some(int) => int.to_ascii_uppercase() as u32,
-					None => return 1
-				};
-				if bint < nint {
-					return -1;
-				}
-				if bint > nint {
-					return 1;
+		let selection = gtk::SingleSelection::new(Some(&sorted));
+		selection.set_autoselect(false);
+		selection.set_can_unselect(true);
+
+		let factory = gtk::SignalListItemFactory::new();
+		factory.connect_setup(|_, list_item| {
+			list_item.set_child(Some(&card::UtopiaCard::new()));
+		});
+		factory.connect_bind(|_, list_item| {
+			if let (Some(card), Some(item)) = (list_item.child(), list_item.item()) {
+				card.downcast_ref::<card::UtopiaCard>()
+					.unwrap()
+					.bind(item.downcast_ref::<UtopiaItem>().unwrap());
+			}
+		});
+		factory.connect_unbind(|_, list_item| {
+			if let Some(card) = list_item.child() {
+				card.downcast_ref::<card::UtopiaCard>().unwrap().unbind();
+			}
+		});
+
+		self_.grid.set_factory(Some(&factory));
+		self_.grid.set_model(Some(&selection));
+
+		self_.filter.set(filter).unwrap();
+		self_.sorter.set(sorter).unwrap();
+		self_.selection.set(selection).unwrap();
+	}
+
+	/// Adds a chip for every status, kinds follow as items show up.
+	fn setup_chips(&self, store: &ItemStore) {
+		for status in chips::STATI.iter() {
+			self.add_chip(chips::Chip::Status(*status));
+		}
+		store
+			.model()
+			.connect_items_changed(glib::clone!(@weak self as grid => move |_, _, _, _| {
+				grid.schedule_count();
+			}));
+	}
+
+	fn add_chip(&self, chip: chips::Chip) {
+		let self_ = imp::UtopiaGrid::from_instance(self);
+		let chip = chips::ChipButton::new(chip);
+		let value = chip.chip.clone();
+		chip.button.connect_toggled(glib::clone!(@weak self as grid => move |button| {
+			let self_ = imp::UtopiaGrid::from_instance(&grid);
+			self_.chips.borrow_mut().set(&value, button.is_active());
+			grid.refilter();
+		}));
+		self_.chip_bar.append(&chip.button);
+		self_.chip_buttons.borrow_mut().push(chip);
+	}
+
+	/// Recounts the chips once the main loop is idle, so that loading a
+	/// large library does not count for every item.
+	fn schedule_count(&self) {
+		let self_ = imp::UtopiaGrid::from_instance(self);
+		if !self_.counts_pending.replace(true) {
+			glib::idle_add_local_once(glib::clone!(@weak self as grid => move || {
+				imp::UtopiaGrid::from_instance(&grid).counts_pending.set(false);
+				grid.update_chips();
+			}));
+		}
+	}
+
+	/// Adds chips for new kinds, drops the ones of kinds no longer in the
+	/// library and counts the items each chip would show along with the
+	/// integration and query.
+	fn update_chips(&self) {
+		let self_ = imp::UtopiaGrid::from_instance(self);
+		let store = match self_.store.get() {
+			Some(store) => store.clone(),
+			None => return
+		};
+		let kinds = store.kind_names();
+		self_.chip_buttons.borrow_mut().retain(|chip| match &chip.chip {
+			chips::Chip::Kind(kind) if !kinds.contains(kind) && !chip.button.is_active() => {
+				self_.chip_bar.remove(&chip.button);
+				false
+			},
+			_ => true
+		});
+		for kind in kinds {
+			let chip = chips::Chip::Kind(kind);
+			if !self_.chip_buttons.borrow().iter().any(|other| other.chip == chip) {
+				self.add_chip(chip);
+			}
+		}
+
+		let model = store.model();
+		let modules = self_.active_modules.borrow();
+		let query = self_.query.borrow();
+		let items: Vec<UtopiaItem> = (0..model.n_items())
+			.filter_map(|position| model.item(position))
+			.filter_map(|item| item.downcast::<UtopiaItem>().ok())
+			.filter(|item| matches(&modules, &query, item).is_some())
+			.collect();
+		for chip in self_.chip_buttons.borrow().iter() {
+			chip.set_count(items.iter().filter(|item| chip.chip.matches(item)).count());
+		}
+	}
+
+	pub fn setup_trigger(&self, dsender: glib::Sender<SidebarMsg>) {
+		let self_ = imp::UtopiaGrid::from_instance(self);
+		self_.grid.connect_activate(glib::clone!(@weak self as grid => move |_, position| {
+			if let Some(item) = grid.item_at(position) {
+				let self_ = imp::UtopiaGrid::from_instance(&grid);
+				let uuid = item.uuid();
+				if let Err(e) = self_.sender.clone().get_mut().unwrap().try_send(crate::uev::UtopiaRequest::TriggerLaunch(uuid.clone())) {
+					eprintln!("Error requesting {} to launch: {}", uuid, e);
 				}
-				// continue if equal
 			}
-			-1
+		}));
+
+		let modules = self_.active_modules.clone();
+		self_.selection.get().unwrap().connect_selected_item_notify(move |selection| {
+			let uuid = selection
+				.selected_item()
+				.map(|item| item.downcast_ref::<UtopiaItem>().unwrap().uuid());
+			dsender.send(SidebarMsg::new(uuid, modules.borrow().clone())).unwrap()
 		});
 	}
 
 	pub fn init(
 		&self,
 		sender: futures::channel::mpsc::Sender<crate::uev::UtopiaRequest>,
+		store: std::rc::Rc<ItemStore>,
 		dsender: glib::Sender<SidebarMsg>
 	) {
 		let self_ = imp::UtopiaGrid::from_instance(self);
 		self_.sender.set(sender).expect("Failed setting up UtopiaGrid");
-		self_
-			.dsender
-			.set(dsender.clone())
-			.expect("Failed setting up UtopiaGrid");
+		self.setup_model(&store);
+		self.setup_chips(&store);
+		self_.store.set(store).expect("Failed setting up UtopiaGrid");
 		self.setup_trigger(dsender);
+
+		let settings = gio::Settings::new(crate::config::APP_ID);
+		settings.connect_changed(
+			Some("sort-order"),
+			glib::clone!(@weak self as grid => move |settings, key| {
+				grid.set_sort_order(SortOrder::from_key(&settings.string(key)));
+			})
+		);
+		self.set_sort_order(SortOrder::from_key(&settings.string("sort-order")));
+		self_.settings.set(settings).expect("Failed setting up UtopiaGrid");
+
+		self_
+			.store
+			.get()
+			.unwrap()
+			.connect_item_changed(glib::clone!(@weak self as grid => move |_, changes| {
+				grid.item_changed(&changes);
+			}));
+		crate::artwork::overrides::connect_changed(glib::clone!(@weak self as grid => move |uuid| {
+			let self_ = imp::UtopiaGrid::from_instance(&grid);
+			if let Some(item) = self_.store.get().and_then(|store| store.item(uuid)) {
+				item.emit_changed();
+			}
+		}));
+		crate::tags::connect_changed(glib::clone!(@weak self as grid => move |_| {
+			let self_ = imp::UtopiaGrid::from_instance(&grid);
+			if !self_.query.borrow().filters.is_empty() {
+				grid.refilter();
+				grid.schedule_count();
+			}
+		}));
 	}
 
-	pub fn insert_card(&self, uuid: String, card: &card::UtopiaCard) {
+	/// Reconsiders an updated item, but only where the filter or order
+	/// depend on what changed. Cards follow their item by themselves.
+	fn item_changed(&self, changes: &ItemChanges) {
 		let self_ = imp::UtopiaGrid::from_instance(self);
-		self_.items.borrow_mut().insert(uuid, card.clone());
-		self_.grid.insert(card, -1);
+		let (refilter, resort) = {
+			let query = self_.query.borrow();
+			let chips = self_.chips.borrow();
+			let searching = !query.text.is_empty();
+			let refilter = (changes.name && searching) ||
+				(changes.kind && (searching || query.uses(Key::Kind) || chips.has(false))) ||
+				(changes.providers &&
+					(searching || query.uses(Key::Provider) || !self_.active_modules.borrow().is_empty())) ||
+				(changes.status && (query.uses(Key::Status) || chips.has(true)));
+			let resort = self_.sort_order.get().depends_on(changes) ||
+				(searching && (changes.kind || changes.providers));
+			(refilter, resort)
+		};
+		if refilter {
+			self_
+				.filter
+				.get()
+				.unwrap()
+				.changed(gtk::FilterChange::Different);
+		}
+		if resort {
+			self_
+				.sorter
+				.get()
+				.unwrap()
+				.changed(gtk::SorterChange::Different);
+		}
+		if changes.kind || changes.providers || changes.status {
+			self.schedule_count();
+		}
 	}
 
-	pub fn update_card(&self, uuid: &String, item: utopia_common::library::LibraryItemFrontend) {
+	pub fn set_sort_order(&self, order: SortOrder) {
 		let self_ = imp::UtopiaGrid::from_instance(self);
-		if let Some(card) = self_.items.borrow().get(uuid) {
-			card.update(item);
+		if self_.sort_order.replace(order) != order {
 			self_
-				.dsender
+				.sorter
 				.get()
 				.unwrap()
-				.send(SidebarMsg::new(
-					Some(card.utopia().clone()),
-					self_.active_module.borrow().clone(),
-					SidebarMsgAction::Update
-				))
-				.unwrap();
+				.changed(gtk::SorterChange::Different);
 		}
 	}
 
-	pub fn update_filter(&self, module: std::cell::Ref<Option<glib::GString>>, search: glib::GString) {
+	/// Item at the position of the filtered and sorted view.
+	fn item_at(&self, position: u32) -> Option<UtopiaItem> {
 		let self_ = imp::UtopiaGrid::from_instance(self);
-		self_.active_module.replace(module.clone());
+		self_
+			.selection
+			.get()
+			.unwrap()
+			.item(position)
+			.and_then(|item| item.downcast::<UtopiaItem>().ok())
+	}
 
-		let module = match module.as_ref() {
-			Some(module) => Some(module.to_owned()),
-			None => None
-		};
-		self_.grid.set_filter_func(move |card| {
-			if let Some(module) = module.as_ref() {
-				if !card.downcast_ref::<card::UtopiaCard>().unwrap().provider(module) {
-					return false;
-				}
-			}
-			if search != "" {
-				return card
-					.downcast_ref::<card::UtopiaCard>()
-					.unwrap()
-					.name()
-					.to_uppercase()
-					.contains(&search.to_uppercase());
-			}
+	/// Toggles every chip off.
+	pub fn clear_chips(&self) {
+		let self_ = imp::UtopiaGrid::from_instance(self);
+		// toggling refilters, which must not find the buttons borrowed
+		let buttons: Vec<gtk::ToggleButton> =
+			self_.chip_buttons.borrow().iter().map(|chip| chip.button.clone()).collect();
+		for button in buttons {
+			button.set_active(false);
+		}
+	}
+
+	pub fn select_item(&self, uuid: &str) -> bool {
+		let self_ = imp::UtopiaGrid::from_instance(self);
+		let selection = self_.selection.get().unwrap();
+		let position = (0..selection.n_items()).find(|position| {
+			self.item_at(*position)
+				.map_or(false, |item| item.uuid() == uuid)
+		});
+		match position {
+			Some(position) => {
+				// reselecting the same item would not emit a change
+				selection.set_selected(gtk::INVALID_LIST_POSITION);
+				selection.set_selected(position);
+				let _ = self_
+					.grid
+					.activate_action("list.scroll-to-item", Some(&position.to_variant()));
+				self_.grid.grab_focus();
+				true
+			},
+			None => false
+		}
+	}
+
+	/// Shows the games of any of the integrations, or of all if there are
+	/// none, that pass the query.
+	pub fn update_filter(&self, modules: Vec<String>, query: Query) {
+		let self_ = imp::UtopiaGrid::from_instance(self);
+		self_.active_modules.replace(modules);
+		self_.query.replace(query);
+		self.refilter();
+		self.schedule_count();
+	}
 
-			true
-		})
+	fn refilter(&self) {
+		let self_ = imp::UtopiaGrid::from_instance(self);
+		self_
+			.filter
+			.get()
+			.unwrap()
+			.changed(gtk::FilterChange::Different);
+		// the ranking of items that stay visible changes as well
+		self_
+			.sorter
+			.get()
+			.unwrap()
+			.changed(gtk::SorterChange::Different);
 	}
 }
+
+/// Whether the item belongs to one of the integrations and passes the
+/// query, with its search score.
+fn matches(modules: &[String], query: &Query, item: &UtopiaItem) -> Option<f64> {
+	if !modules.is_empty() && !modules.iter().any(|module| item.provider(module)) {
+		return None;
+	}
+	if !query.matches(item) {
+		return None;
+	}
+	if query.text.is_empty() {
+		return Some(0.0);
+	}
+	score(query, item)
+}
+
+/// Search score of the item, remembered by the item until the text or
+/// the item change. Filter and sorter may ask in any order.
+fn score(query: &Query, item: &UtopiaItem) -> Option<f64> {
+	item.search_score(&query.text, |item| search::score(&query.text, item))
+}
@@ -0,0 +1,36 @@
+use crate::{fuzzy, store::UtopiaItem};
+
+/// Matches of other fields count less than ones of the name.
+const PROVIDER_WEIGHT: f64 = 0.7;
+const KIND_WEIGHT: f64 = 0.6;
+const UUID_WEIGHT: f64 = 0.5;
+
+fn best(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+	match (a, b) {
+		(Some(a), Some(b)) => Some(a.max(b)),
+		(a, b) => a.or(b)
+	}
+}
+
+/// Ranks the item for the [normalized](fuzzy::normalize) query by its
+/// name, provider names, kind and uuid. `None` if nothing matches.
+pub fn score(query: &str, item: &UtopiaItem) -> Option<f64> {
+	let details = item.details();
+	let mut score = fuzzy::match_score(query, &fuzzy::normalize(&details.name));
+	for provider in details.providers.values() {
+		score = best(
+			score,
+			fuzzy::match_score(query, &fuzzy::normalize(&provider.name)).map(|score| score * PROVIDER_WEIGHT)
+		);
+	}
+	score = best(
+		score,
+		fuzzy::match_score(query, &fuzzy::normalize(&item.kind_name())).map(|score| score * KIND_WEIGHT)
+	);
+	// uuids are matched literally, fuzzy matches on them are noise
+	let compact: String = query.chars().filter(|c| *c != ' ').collect();
+	if compact.len() >= 4 && details.uuid.to_lowercase().replace('-', "").contains(&compact) {
+		score = best(score, Some(UUID_WEIGHT));
+	}
+	score
+}
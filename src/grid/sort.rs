@@ -0,0 +1,77 @@
+use std::cmp::Ordering;
+
+use utopia_common::library::LibraryItemStatus;
+
+use crate::{history,
+            store::{ItemChanges, UtopiaItem}};
+
+/// Order of the library, stored as `sort-order` in the settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+	Name,
+	LastPlayed,
+	RecentlyAdded,
+	Provider,
+	Status
+}
+
+impl Default for SortOrder {
+	fn default() -> Self {
+		Self::Name
+	}
+}
+
+impl SortOrder {
+	pub fn from_key(key: &str) -> Self {
+		match key {
+			"last-played" => Self::LastPlayed,
+			"recently-added" => Self::RecentlyAdded,
+			"provider" => Self::Provider,
+			"status" => Self::Status,
+			_ => Self::Name
+		}
+	}
+
+	/// Whether the order of an item may change with the update. Names
+	/// break ties in every order.
+	pub fn depends_on(&self, changes: &ItemChanges) -> bool {
+		changes.name ||
+			match self {
+				Self::Name | Self::RecentlyAdded => false,
+				// starting a game counts as playing it
+				Self::LastPlayed | Self::Provider | Self::Status => changes.status
+			}
+	}
+
+	/// Compares two items, falling back to their names and uuids so that
+	/// the order is total.
+	pub fn compare(&self, a: &UtopiaItem, b: &UtopiaItem) -> Ordering {
+		let primary = match self {
+			Self::Name => Ordering::Equal,
+			// most recent first, never played last
+			Self::LastPlayed => history::entry(&b.uuid()).played.cmp(&history::entry(&a.uuid()).played),
+			Self::RecentlyAdded => history::entry(&b.uuid()).added.cmp(&history::entry(&a.uuid()).added),
+			Self::Provider => a.provider_collation_key().cmp(&b.provider_collation_key()),
+			Self::Status => status_rank(a).cmp(&status_rank(b))
+		};
+		primary
+			.then_with(|| a.collation_key().cmp(&b.collation_key()))
+			.then_with(|| a.uuid().cmp(&b.uuid()))
+	}
+}
+
+/// Running games first, then the ones with pending updates.
+fn status_rank(item: &UtopiaItem) -> u8 {
+	item.details()
+		.active_provider
+		.stati
+		.iter()
+		.map(|status| match status {
+			LibraryItemStatus::Running(_) | LibraryItemStatus::Closing => 0,
+			LibraryItemStatus::Updatable => 1,
+			LibraryItemStatus::Updating => 2,
+			LibraryItemStatus::Installed => 3
+		})
+		.min()
+		.unwrap_or(4)
+}
@@ -0,0 +1,93 @@
+use std::{cell::RefCell,
+          collections::HashMap,
+          path::PathBuf,
+          time::{SystemTime, UNIX_EPOCH}};
+
+/// When an item was first seen and last launched, in seconds since the
+/// epoch.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Entry {
+	pub added: i64,
+	pub played: Option<i64>
+}
+
+thread_local! {
+	static HISTORY: RefCell<HashMap<String, Entry>> = RefCell::new(load());
+}
+
+fn path() -> PathBuf {
+	crate::persist::path("history.json")
+}
+
+fn now() -> i64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |time| time.as_secs() as i64)
+}
+
+fn load() -> HashMap<String, Entry> {
+	crate::persist::load(&path())
+		.into_iter()
+		.filter_map(|(uuid, entry)| {
+			Some((uuid, Entry {
+				added: entry.get("added")?.as_i64()?,
+				played: entry.get("played").and_then(|played| played.as_i64())
+			}))
+		})
+		.collect()
+}
+
+fn entries() -> serde_json::Map<String, serde_json::Value> {
+	HISTORY.with(|history| {
+		history
+			.borrow()
+			.iter()
+			.map(|(uuid, entry)| {
+				(uuid.clone(), serde_json::json!({
+					"added": entry.added,
+					"played": entry.played
+				}))
+			})
+			.collect()
+	})
+}
+
+/// Writes the history shortly after, so that loading a large library
+/// does not write it for every item.
+fn schedule_save() {
+	crate::persist::schedule_save(path(), entries);
+}
+
+pub fn entry(uuid: &str) -> Entry {
+	HISTORY.with(|history| history.borrow().get(uuid).copied().unwrap_or_default())
+}
+
+/// Remembers when the item was first seen, if it's new.
+pub fn record_added(uuid: &str) {
+	let added = HISTORY.with(|history| {
+		let mut history = history.borrow_mut();
+		if history.contains_key(uuid) {
+			return false;
+		}
+		history.insert(uuid.to_owned(), Entry {
+			added: now(),
+			played: None
+		});
+		true
+	});
+	if added {
+		schedule_save();
+	}
+}
+
+pub fn record_played(uuid: &str) {
+	HISTORY.with(|history| {
+		let mut history = history.borrow_mut();
+		let entry = history.entry(uuid.to_owned()).or_insert_with(|| Entry {
+			added: now(),
+			played: None
+		});
+		entry.played = Some(now());
+	});
+	schedule_save();
+}
@@ -0,0 +1,203 @@
+use std::{cell::RefCell, path::{Path, PathBuf}, rc::Rc};
+
+use gtk::{gio, glib, pango, prelude::*};
+
+use crate::fuzzy;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif", "bmp", "tga", "tif", "tiff"];
+/// Matches below this are not proposed at all.
+const MIN_SCORE: f64 = 0.6;
+/// Matches from this on are accepted unless the user unticks them.
+const ACCEPT_SCORE: f64 = 0.85;
+
+/// An image file proposed as cover of a library item.
+#[derive(Debug, Clone)]
+pub struct Proposal {
+	pub path: PathBuf,
+	pub uuid: String,
+	pub name: String,
+	pub score: f64
+}
+
+/// Matches the images in `dir` against the library items, given as
+/// uuid and name. Files named after an uuid match exactly, all others
+/// are matched fuzzily by name. Every item gets its best file only.
+pub fn scan(dir: &Path, items: &[(String, String)]) -> Result<Vec<Proposal>, String> {
+	let items: Vec<(&String, &String, String)> = items
+		.iter()
+		.map(|(uuid, name)| (uuid, name, fuzzy::normalize(name)))
+		.collect();
+	let mut proposals: Vec<Proposal> = Vec::new();
+	for entry in std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))? {
+		let path = match entry {
+			Ok(entry) => entry.path(),
+			Err(e) => {
+				eprintln!("Skipping entry of {}: {}", dir.display(), e);
+				continue;
+			}
+		};
+		let is_image = path
+			.extension()
+			.and_then(|ext| ext.to_str())
+			.map_or(false, |ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+		let stem = match path.file_stem().and_then(|stem| stem.to_str()) {
+			Some(stem) if is_image && path.is_file() => stem.to_owned(),
+			_ => continue
+		};
+
+		let normalized = fuzzy::normalize(&stem);
+		let best = items
+			.iter()
+			.map(|(uuid, name, normalized_name)| {
+				let score = if uuid.eq_ignore_ascii_case(&stem) {
+					1.0
+				} else {
+					fuzzy::similarity(&normalized, normalized_name)
+				};
+				(uuid, name, score)
+			})
+			.max_by(|(_, _, a), (_, _, b)| a.partial_cmp(b).unwrap());
+		if let Some((uuid, name, score)) = best.filter(|(_, _, score)| *score >= MIN_SCORE) {
+			match proposals.iter_mut().find(|proposal| &proposal.uuid == *uuid) {
+				Some(existing) if existing.score >= score => {},
+				Some(existing) => {
+					existing.path = path;
+					existing.score = score;
+				},
+				None => proposals.push(Proposal {
+					path,
+					uuid: uuid.to_string(),
+					name: name.to_string(),
+					score
+				})
+			}
+		}
+	}
+	proposals.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+	Ok(proposals)
+}
+
+fn proposal_row(proposal: &Proposal) -> (gtk::Box, gtk::CheckButton) {
+	let row = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+	row.set_margin_top(6);
+	row.set_margin_bottom(6);
+	row.set_margin_start(6);
+	row.set_margin_end(6);
+
+	let check = gtk::CheckButton::new();
+	check.set_active(proposal.score >= ACCEPT_SCORE);
+	row.append(&check);
+
+	let labels = gtk::Box::new(gtk::Orientation::Vertical, 0);
+	labels.set_hexpand(true);
+	let name = gtk::Label::new(Some(&proposal.name));
+	name.set_xalign(0.0);
+	name.set_ellipsize(pango::EllipsizeMode::End);
+	name.add_css_class("heading");
+	labels.append(&name);
+	let file = gtk::Label::new(proposal.path.file_name().and_then(|name| name.to_str()));
+	file.set_xalign(0.0);
+	file.set_ellipsize(pango::EllipsizeMode::Middle);
+	file.add_css_class("dim-label");
+	labels.append(&file);
+	row.append(&labels);
+
+	let score = gtk::Label::new(Some(&format!("{:.0} %", proposal.score * 100.0)));
+	score.add_css_class("dim-label");
+	row.append(&score);
+	(row, check)
+}
+
+/// Lists the proposals for review, applying the ticked ones as cover
+/// overrides.
+pub fn review(parent: &gtk::Window, proposals: Vec<Proposal>) {
+	let diag = gtk::Dialog::with_buttons(
+		Some("Import Artwork"),
+		Some(parent),
+		gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::USE_HEADER_BAR,
+		&[("_Cancel", gtk::ResponseType::Cancel), ("_Import", gtk::ResponseType::Accept)]
+	);
+	diag.set_default_size(480, 560);
+	diag.set_default_response(gtk::ResponseType::Accept);
+
+	let content = diag.content_area();
+	if proposals.is_empty() {
+		let empty = gtk::Label::new(Some("No image in this folder matches a game of the library."));
+		empty.set_wrap(true);
+		empty.set_margin_top(24);
+		empty.set_margin_bottom(24);
+		empty.set_margin_start(24);
+		empty.set_margin_end(24);
+		content.append(&empty);
+		diag.set_response_sensitive(gtk::ResponseType::Accept, false);
+	} else {
+		let list = gtk::ListBox::new();
+		list.set_selection_mode(gtk::SelectionMode::None);
+		list.set_show_separators(true);
+		let scrolled = gtk::ScrolledWindowBuilder::new()
+			.hscrollbar_policy(gtk::PolicyType::Never)
+			.vexpand(true)
+			.child(&list)
+			.build();
+		content.append(&scrolled);
+
+		let mut checks = Vec::with_capacity(proposals.len());
+		for proposal in proposals {
+			let (row, check) = proposal_row(&proposal);
+			list.append(&row);
+			checks.push((proposal, check));
+		}
+
+		diag.connect_response(move |_, response| {
+			if response != gtk::ResponseType::Accept {
+				return;
+			}
+			for (proposal, _) in checks.iter().filter(|(_, check)| check.is_active()) {
+				let uuid = proposal.uuid.clone();
+				crate::artwork::overrides::set(&proposal.uuid, &gio::File::for_path(&proposal.path), move |res| {
+					if let Err(e) = res {
+						eprintln!("Error importing cover of {}: {}", uuid, e);
+					}
+				});
+			}
+		});
+	}
+	diag.connect_response(|diag, _| diag.close());
+	diag.show();
+}
+
+/// Asks for a folder of artwork, matches it against the library items
+/// and lets the user review the matches.
+pub fn run(parent: &gtk::Window, items: Vec<(String, String)>) {
+	let chooser = gtk::FileChooserNative::new(
+		Some("Import Artwork"),
+		Some(parent),
+		gtk::FileChooserAction::SelectFolder,
+		Some("_Select"),
+		Some("_Cancel")
+	);
+	chooser.set_modal(true);
+
+	// native dialogs are not kept alive by GTK
+	let keep_alive = Rc::new(RefCell::new(None));
+	chooser.connect_response(glib::clone!(@weak parent, @strong keep_alive => move |chooser, response| {
+		keep_alive.replace(None);
+		if response != gtk::ResponseType::Accept {
+			return;
+		}
+		let dir = match chooser.file().and_then(|dir| dir.path()) {
+			Some(dir) => dir,
+			None => return
+		};
+		let items = items.clone();
+		crate::artwork::spawn_worker(
+			move || scan(&dir, &items),
+			glib::clone!(@weak parent => move |proposals| match proposals.and_then(|proposals| proposals) {
+				Ok(proposals) => review(&parent, proposals),
+				Err(e) => eprintln!("Error scanning artwork: {}", e)
+			})
+		);
+	}));
+	chooser.show();
+	keep_alive.replace(Some(chooser));
+}
@@ -1,19 +1,43 @@
 use gtk::{gio, glib, prelude::*, subclass::prelude::*, CompositeTemplate};
 
+use crate::{artwork::{ProviderIcon, ScaledTexture},
+            integrations::IntegrationLayout,
+            store::UtopiaIntegration};
+
+/// Size of the icon in the sidebar, before the scale factor.
+const ICON_SIZE: i32 = 32;
+
 mod imp {
-	use gtk::{Image, Label, ListBoxRow};
+	use gtk::{Button, CheckButton, Image, Label, ListBoxRow};
 
 	use super::*;
 
 	#[derive(Debug, Default, CompositeTemplate)]
 	#[template(resource = "/dev/sp1rit/Utopia/ui/integration_item.ui")]
 	pub struct UtopiaIntegrationItem {
+		pub integration: std::cell::RefCell<Option<UtopiaIntegration>>,
+		pub changed_handler: std::cell::RefCell<Option<glib::SignalHandlerId>>,
+		pub context_menu: once_cell::unsync::OnceCell<gtk::PopoverMenu>,
+		/// name and icon of the integration the icon was loaded for
+		pub shown_icon: std::cell::RefCell<Option<(String, Option<String>)>>,
+		pub layout: IntegrationLayout,
+
+		#[template_child]
+		pub check: TemplateChild<CheckButton>,
 		#[template_child]
 		pub icon: TemplateChild<Image>,
 		#[template_child]
 		pub name: TemplateChild<Label>,
 		#[template_child]
-		pub uuid: TemplateChild<Label>
+		pub uuid: TemplateChild<Label>,
+		#[template_child]
+		pub running: TemplateChild<Label>,
+		#[template_child]
+		pub updatable: TemplateChild<Label>,
+		#[template_child]
+		pub count: TemplateChild<Label>,
+		#[template_child]
+		pub prefs_btn: TemplateChild<Button>
 	}
 
 	#[glib::object_subclass]
@@ -35,6 +59,22 @@ mod imp {
 	impl ObjectImpl for UtopiaIntegrationItem {
 		fn constructed(&self, obj: &Self::Type) {
 			self.parent_constructed(obj);
+			obj.setup_context_menu();
+			obj.setup_drag();
+			obj.connect_scale_factor_notify(|item| {
+				// rendered for the previous scale factor
+				imp::UtopiaIntegrationItem::from_instance(item).shown_icon.replace(None);
+				item.refresh();
+			});
+		}
+
+		fn dispose(&self, _obj: &Self::Type) {
+			if let Some(menu) = self.context_menu.get() {
+				menu.unparent();
+			}
+			if let (Some(integration), Some(handler)) = (self.integration.take(), self.changed_handler.take()) {
+				integration.disconnect(handler);
+			}
 		}
 	}
 
@@ -53,10 +93,145 @@ impl UtopiaIntegrationItem {
 		glib::Object::new(&[]).expect("Failed to create UtopiaIntegrationItem")
 	}
 
-	pub fn init(&self, uuid: &str, name: &str, icon: Option<&str>) {
+	/// Opens a menu with the actions of the integration on right click.
+	fn setup_context_menu(&self) {
+		let self_ = imp::UtopiaIntegrationItem::from_instance(self);
+		let menu = gtk::PopoverMenu::from_model(None::<&gio::MenuModel>);
+		menu.set_parent(self);
+		menu.set_has_arrow(false);
+		menu.set_position(gtk::PositionType::Bottom);
+		menu.set_halign(gtk::Align::Start);
+
+		let click = gtk::GestureClick::new();
+		click.set_button(gtk::gdk::BUTTON_SECONDARY);
+		click.connect_pressed(glib::clone!(@weak menu => move |_, _, x, y| {
+			menu.set_pointing_to(Some(&gtk::gdk::Rectangle {
+				x: x as i32,
+				y: y as i32,
+				width: 1,
+				height: 1
+			}));
+			menu.popup();
+		}));
+		self.add_controller(&click);
+		self_.context_menu.set(menu).unwrap();
+	}
+
+	/// Lets the row be dragged by the uuid of its integration.
+	fn setup_drag(&self) {
+		let drag = gtk::DragSource::new();
+		drag.set_actions(gtk::gdk::DragAction::MOVE);
+		drag.connect_prepare(glib::clone!(@weak self as item => @default-return None, move |_, _, _| {
+			item.uuid().map(|uuid| gtk::gdk::ContentProvider::for_value(&uuid.to_value()))
+		}));
+		drag.connect_drag_begin(glib::clone!(@weak self as item => move |drag, _| {
+			drag.set_icon(Some(&gtk::WidgetPaintable::new(Some(&item))), 0, 0);
+		}));
+		self.add_controller(&drag);
+	}
+
+	/// Runs `f` with the uuid of an integration dropped onto the row.
+	pub fn connect_dropped<F: Fn(&Self, String) + 'static>(&self, f: F) {
+		let drop = gtk::DropTarget::new(String::static_type(), gtk::gdk::DragAction::MOVE);
+		drop.connect_drop(glib::clone!(@weak self as item => @default-return false, move |_, value, _, _| {
+			match value.get::<String>() {
+				Ok(uuid) => {
+					f(&item, uuid);
+					true
+				},
+				Err(_) => false
+			}
+		}));
+		self.add_controller(&drop);
+	}
+
+	/// Shows the integration, following its changes.
+	pub fn bind(&self, integration: &UtopiaIntegration) {
+		let self_ = imp::UtopiaIntegrationItem::from_instance(self);
+		let handler = integration.connect_changed(glib::clone!(@weak self as item => move |_| item.refresh()));
+		if let (Some(integration), Some(handler)) = (
+			self_.integration.replace(Some(integration.clone())),
+			self_.changed_handler.replace(Some(handler))
+		) {
+			integration.disconnect(handler);
+		}
+
+		let target = integration.uuid().to_variant();
+		self_.prefs_btn.set_action_target_value(Some(&target));
+		let menu = gio::Menu::new();
+		for (label, action) in &[
+			("_Preferences", "app.integration-preferences"),
+			("_Rename…", "win.rename-integration"),
+			("_Hide", "win.hide-integration")
+		] {
+			let item = gio::MenuItem::new(Some(label), None);
+			item.set_action_and_target_value(Some(action), Some(&target));
+			menu.append_item(&item);
+		}
+		if let Some(context_menu) = self_.context_menu.get() {
+			context_menu.set_menu_model(Some(&menu));
+		}
+		self.refresh();
+	}
+
+	/// Updates the row from its integration and the users layout.
+	pub fn refresh(&self) {
+		let self_ = imp::UtopiaIntegrationItem::from_instance(self);
+		let integration = match self_.integration.borrow().clone() {
+			Some(integration) => integration,
+			None => return
+		};
+		let info = integration.info();
+		self_.name.set_label(&self_.layout.label(&info.uuid, &info.name));
+		self_.uuid.set_label(&info.uuid);
+		let icon = Some((info.name.clone(), info.icon.clone()));
+		if *self_.shown_icon.borrow() != icon {
+			self_.shown_icon.replace(icon);
+			self.load_icon(&info.uuid, &info.name, info.icon.as_deref());
+		}
+		self_.count.set_label(&info.count.to_string());
+		self_.running.set_visible(info.running > 0);
+		self_.running.set_label(&info.running.to_string());
+		self_.running.set_tooltip_text(Some(&format!("{} running", info.running)));
+		self_.updatable.set_visible(info.updatable > 0);
+		self_.updatable.set_label(&info.updatable.to_string());
+		self_
+			.updatable
+			.set_tooltip_text(Some(&format!("{} with updates available", info.updatable)));
+	}
+
+	fn load_icon(&self, uuid: &str, name: &str, icon: Option<&str>) {
+		let scale = self.scale_factor();
+		let image = imp::UtopiaIntegrationItem::from_instance(self).icon.get();
+		crate::artwork::load_provider_icon(
+			self.upcast_ref(),
+			uuid,
+			name,
+			icon,
+			scale * ICON_SIZE,
+			glib::clone!(@weak image => move |icon| match icon {
+				ProviderIcon::Named(name) => image.set_icon_name(Some(&name)),
+				ProviderIcon::Texture(texture) => image.set_paintable(Some(&ScaledTexture::new(&texture, scale)))
+			})
+		);
+	}
+
+	pub fn uuid(&self) -> Option<String> {
+		let self_ = imp::UtopiaIntegrationItem::from_instance(self);
+		self_.integration.borrow().as_ref().map(UtopiaIntegration::uuid)
+	}
+
+	pub fn set_checked(&self, checked: bool) {
+		let self_ = imp::UtopiaIntegrationItem::from_instance(self);
+		self_.check.set_active(checked);
+	}
+
+	/// Runs `f` whenever the checkbox, which adds the integration to the
+	/// shown ones, gets toggled.
+	pub fn connect_checked<F: Fn(&Self, bool) + 'static>(&self, f: F) {
 		let self_ = imp::UtopiaIntegrationItem::from_instance(self);
-		self_.name.set_label(name);
-		self_.uuid.set_label(uuid);
-		self_.icon.set_icon_name(icon);
+		self_
+			.check
+			.connect_toggled(glib::clone!(@weak self as item => move |check| f(&item, check.is_active())));
 	}
 }
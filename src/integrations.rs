@@ -0,0 +1,166 @@
+use std::{cell::RefCell,
+          collections::{HashMap, HashSet},
+          rc::Rc};
+
+use gtk::{gio, prelude::*};
+
+const ORDER: &str = "integration-order";
+const HIDDEN: &str = "hidden-integrations";
+const LABELS: &str = "integration-labels";
+
+/// The keys as parsed from the settings.
+#[derive(Debug)]
+struct State {
+	/// uuids in the users order
+	order: Vec<String>,
+	positions: HashMap<String, usize>,
+	hidden: HashSet<String>,
+	labels: HashMap<String, String>
+}
+
+impl State {
+	fn load(settings: &gio::Settings) -> Self {
+		let order = strv(settings, ORDER);
+		Self {
+			positions: order.iter().enumerate().map(|(index, uuid)| (uuid.clone(), index)).collect(),
+			order,
+			hidden: strv(settings, HIDDEN).into_iter().collect(),
+			labels: settings.value(LABELS).get().unwrap_or_default()
+		}
+	}
+}
+
+fn strv(settings: &gio::Settings, key: &str) -> Vec<String> {
+	settings.strv(key).iter().map(|uuid| uuid.to_string()).collect()
+}
+
+thread_local! {
+	static LAYOUT: IntegrationLayout = IntegrationLayout::load();
+}
+
+/// How the user arranged the integrations: their order, which ones are
+/// hidden and what they are called. Stored in the settings, every widget
+/// shares the same instance.
+#[derive(Debug, Clone)]
+pub struct IntegrationLayout {
+	settings: gio::Settings,
+	state: Rc<RefCell<State>>
+}
+
+impl Default for IntegrationLayout {
+	/// The shared layout.
+	fn default() -> Self {
+		LAYOUT.with(Clone::clone)
+	}
+}
+
+impl IntegrationLayout {
+	fn load() -> Self {
+		let settings = gio::Settings::new(crate::config::APP_ID);
+		let state = Rc::new(RefCell::new(State::load(&settings)));
+		// connected first, so other handlers see the new state
+		let loaded = state.clone();
+		settings.connect_changed(None, move |settings, key| {
+			if key == ORDER || key == HIDDEN || key == LABELS {
+				loaded.replace(State::load(settings));
+			}
+		});
+		Self {
+			settings,
+			state
+		}
+	}
+
+	fn set_strv(&self, key: &str, values: &[String]) {
+		let values: Vec<&str> = values.iter().map(String::as_str).collect();
+		if let Err(e) = self.settings.set_strv(key, &values) {
+			eprintln!("Failed saving {}: {}", key, e);
+		}
+	}
+
+	/// Runs `f` whenever the order, hidden integrations or labels change.
+	pub fn connect_changed<F: Fn() + 'static>(&self, f: F) {
+		self.settings.connect_changed(None, move |_, key| {
+			if key == ORDER || key == HIDDEN || key == LABELS {
+				f();
+			}
+		});
+	}
+
+	/// The label the user gave the integration, or its own name.
+	pub fn label(&self, uuid: &str, name: &str) -> String {
+		self.state
+			.borrow()
+			.labels
+			.get(uuid)
+			.cloned()
+			.unwrap_or_else(|| name.to_owned())
+	}
+
+	/// Names the integration locally, an empty label restores its own
+	/// name.
+	pub fn set_label(&self, uuid: &str, label: &str) {
+		let mut labels = self.state.borrow().labels.clone();
+		if label.trim().is_empty() {
+			labels.remove(uuid);
+		} else {
+			labels.insert(uuid.to_owned(), label.trim().to_owned());
+		}
+		if let Err(e) = self.settings.set_value(LABELS, &labels.to_variant()) {
+			eprintln!("Failed saving {}: {}", LABELS, e);
+		}
+	}
+
+	pub fn is_hidden(&self, uuid: &str) -> bool {
+		self.state.borrow().hidden.contains(uuid)
+	}
+
+	pub fn has_hidden(&self) -> bool {
+		!self.state.borrow().hidden.is_empty()
+	}
+
+	pub fn set_hidden(&self, uuid: &str, hidden: bool) {
+		let mut uuids: Vec<String> = self
+			.state
+			.borrow()
+			.hidden
+			.iter()
+			.filter(|other| *other != uuid)
+			.cloned()
+			.collect();
+		if hidden {
+			uuids.push(uuid.to_owned());
+		}
+		self.set_strv(HIDDEN, &uuids);
+	}
+
+	pub fn unhide_all(&self) {
+		self.set_strv(HIDDEN, &[]);
+	}
+
+	/// Position of the integration in the users order. Integrations the
+	/// user never moved come last.
+	pub fn position(&self, uuid: &str) -> usize {
+		let state = self.state.borrow();
+		state.positions.get(uuid).copied().unwrap_or(state.order.len())
+	}
+
+	/// Moves the integration in front of another one, or to the end.
+	/// `current` is the order as shown, which includes integrations the
+	/// user never moved.
+	pub fn move_before(&self, current: &[String], uuid: &str, before: Option<&str>) {
+		let mut order: Vec<String> = current.iter().filter(|other| *other != uuid).cloned().collect();
+		let index = before
+			.and_then(|before| order.iter().position(|other| other == before))
+			.unwrap_or(order.len());
+		order.insert(index, uuid.to_owned());
+		// keep integrations that aren't around right now where they were
+		let saved = self.state.borrow().order.clone();
+		for (index, other) in saved.into_iter().enumerate() {
+			if !order.contains(&other) {
+				order.insert(index.min(order.len()), other);
+			}
+		}
+		self.set_strv(ORDER, &order);
+	}
+}
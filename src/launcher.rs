@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+use gtk::{gdk, glib, prelude::*};
+
+use crate::{config::APP_ID, uri::UtopiaUri};
+
+const GROUP: &str = "Desktop Entry";
+
+/// Whether the uuid can be used as is in file names and desktop ids,
+/// which allow letters, digits, `-`, `_` and `.` only.
+fn is_file_name(uuid: &str) -> bool {
+	!uuid.is_empty() &&
+		!uuid.starts_with('.') &&
+		uuid.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn desktop_file(uuid: &str) -> PathBuf {
+	glib::user_data_dir()
+		.join("applications")
+		.join(format!("{}.game-{}.desktop", APP_ID, uuid))
+}
+
+fn icon_file(uuid: &str) -> PathBuf {
+	glib::user_data_dir()
+		.join("gtopia")
+		.join("icons")
+		.join(format!("{}.png", uuid))
+}
+
+fn io_error(e: std::io::Error) -> glib::Error {
+	glib::Error::new(glib::FileError::Failed, &e.to_string())
+}
+
+/// Quotes an argument of `Exec` the way the desktop entry spec asks for,
+/// field codes included.
+fn quote_exec_arg(arg: &str) -> String {
+	let mut quoted = String::from("\"");
+	for c in arg.chars() {
+		match c {
+			'"' | '`' | '$' | '\\' => {
+				quoted.push('\\');
+				quoted.push(c);
+			},
+			'%' => quoted.push_str("%%"),
+			c => quoted.push(c)
+		}
+	}
+	quoted.push('"');
+	quoted
+}
+
+/// Writes a `.desktop` file for a single game into the users
+/// application directory, so it shows up in the regular app grid.
+/// Launching goes through the `gtopia://launch/` link.
+pub fn create_launcher(uuid: &str, name: &str, cover: Option<&gdk::Texture>) -> Result<PathBuf, glib::Error> {
+	if !is_file_name(uuid) {
+		return Err(glib::Error::new(
+			glib::FileError::Inval,
+			&format!("“{}” can't be used in a launcher", uuid.escape_debug())
+		));
+	}
+	let path = desktop_file(uuid);
+	if let Some(dir) = path.parent() {
+		std::fs::create_dir_all(dir).map_err(io_error)?;
+	}
+
+	let icon = match cover {
+		Some(cover) => {
+			let icon = icon_file(uuid);
+			if let Some(dir) = icon.parent() {
+				std::fs::create_dir_all(dir).map_err(io_error)?;
+			}
+			if cover.save_to_png(&icon) {
+				icon.to_string_lossy().into_owned()
+			} else {
+				eprintln!("Failed exporting cover of {} as icon", uuid);
+				String::from(APP_ID)
+			}
+		},
+		None => String::from(APP_ID)
+	};
+
+	let entry = glib::KeyFile::new();
+	entry.set_string(GROUP, "Type", "Application");
+	entry.set_string(GROUP, "Name", name);
+	entry.set_string(GROUP, "Comment", &format!("Launch {} with µtopia", name));
+	entry.set_string(GROUP, "Icon", &icon);
+	entry.set_string(
+		GROUP,
+		"Exec",
+		&format!("gtopia {}", quote_exec_arg(&UtopiaUri::Launch(uuid.to_owned()).to_uri()))
+	);
+	entry.set_boolean(GROUP, "Terminal", false);
+	entry.set_string(GROUP, "Categories", "Game;");
+	entry.set_string(GROUP, "X-Gtopia-Uuid", uuid);
+	entry.save_to_file(&path)?;
+
+	Ok(path)
+}
@@ -1,9 +1,22 @@
 mod application;
+mod artwork;
 pub mod config;
+mod dbus;
 mod detail;
+mod fuzzy;
 pub mod grid;
+mod history;
+mod import;
 pub mod integration_item;
+mod integrations;
+mod launcher;
+mod notifications;
+mod persist;
+mod query;
+pub mod store;
+mod tags;
 mod uev;
+pub mod uri;
 pub mod utopia;
 
 pub mod preferences;
@@ -0,0 +1,138 @@
+use std::{cell::RefCell,
+          collections::{HashMap, HashSet}};
+
+use gtk::{gio, glib::ToVariant, prelude::*};
+use utopia_common::library::{LibraryItemFrontend, LibraryItemStatus};
+
+use crate::uev::UtopiaMessage;
+
+thread_local! {
+	static QUITTING: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+/// Marks the game as asked to close or be killed, so it stopping isn't
+/// reported as unexpected exit.
+pub fn expect_exit(uuid: &str) {
+	QUITTING.with(|quitting| quitting.borrow_mut().insert(uuid.to_owned()));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LifecycleEvent {
+	UpdateFinished,
+	Updatable,
+	UnexpectedExit
+}
+
+impl LifecycleEvent {
+	fn settings_key(&self) -> &'static str {
+		match self {
+			Self::UpdateFinished => "notify-update-finished",
+			Self::Updatable => "notify-updatable",
+			Self::UnexpectedExit => "notify-unexpected-exit"
+		}
+	}
+
+	fn notification(&self, item: &LibraryItemFrontend) -> gio::Notification {
+		let uuid = item.uuid.to_variant();
+		match self {
+			Self::UpdateFinished => {
+				let notification = gio::Notification::new(&format!("{} finished updating", item.name));
+				notification.set_body(Some("The game is ready to be played."));
+				notification.add_button_with_target_value("Launch", "app.launch", Some(&uuid));
+				notification.set_default_action_and_target_value("app.show-game", Some(&uuid));
+				notification
+			},
+			Self::Updatable => {
+				let notification = gio::Notification::new(&format!("Update available for {}", item.name));
+				notification.set_body(Some(&format!("{} can be updated.", item.active_provider.name)));
+				notification.add_button_with_target_value("Show", "app.show-game", Some(&uuid));
+				notification.set_default_action_and_target_value("app.show-game", Some(&uuid));
+				notification
+			},
+			Self::UnexpectedExit => {
+				let notification = gio::Notification::new(&format!("{} exited unexpectedly", item.name));
+				notification.set_body(Some("The game stopped without being asked to close."));
+				notification.set_priority(gio::NotificationPriority::High);
+				notification.add_button_with_target_value("Launch", "app.launch", Some(&uuid));
+				notification.set_default_action_and_target_value("app.show-game", Some(&uuid));
+				notification
+			}
+		}
+	}
+}
+
+fn has(stati: &[LibraryItemStatus], status: LibraryItemStatus) -> bool {
+	stati
+		.iter()
+		.any(|i| std::mem::discriminant(i) == std::mem::discriminant(&status))
+}
+
+fn transitions(old: &[LibraryItemStatus], new: &[LibraryItemStatus]) -> Vec<LifecycleEvent> {
+	let mut events = Vec::new();
+	if has(old, LibraryItemStatus::Updating) &&
+		!has(new, LibraryItemStatus::Updating) &&
+		has(new, LibraryItemStatus::Installed)
+	{
+		events.push(LifecycleEvent::UpdateFinished);
+	}
+	if !has(old, LibraryItemStatus::Updatable) && has(new, LibraryItemStatus::Updatable) {
+		events.push(LifecycleEvent::Updatable);
+	}
+	// a regular close passes through Closing first
+	if has(old, LibraryItemStatus::Running(None)) &&
+		!has(old, LibraryItemStatus::Closing) &&
+		!has(new, LibraryItemStatus::Running(None)) &&
+		!has(new, LibraryItemStatus::Closing)
+	{
+		events.push(LifecycleEvent::UnexpectedExit);
+	}
+	events
+}
+
+/// Sends desktop notifications for status changes of library items
+/// that happen while the card might be out of sight.
+#[derive(Debug, Default)]
+pub struct UtopiaNotifier {
+	stati: RefCell<HashMap<String, (String, Vec<LibraryItemStatus>)>>
+}
+
+impl UtopiaNotifier {
+	pub fn observe(&self, application: &gio::Application, settings: &gio::Settings, msg: &UtopiaMessage) {
+		match msg {
+			UtopiaMessage::RefreshGameLibrary(library) => {
+				self.stati.replace(
+					library
+						.iter()
+						.map(|item| {
+							let provider = &item.active_provider;
+							(item.uuid.clone(), (provider.uuid.clone(), provider.stati.clone()))
+						})
+						.collect()
+				);
+			},
+			UtopiaMessage::UpdateGame(item) => {
+				let old = self.stati.borrow_mut().insert(
+					item.uuid.clone(),
+					(item.active_provider.uuid.clone(), item.active_provider.stati.clone())
+				);
+				let stopped = !has(&item.active_provider.stati, LibraryItemStatus::Running(None));
+				let expected = stopped && QUITTING.with(|quitting| quitting.borrow_mut().remove(&item.uuid));
+				// switching the active provider is not a status transition
+				if let Some((_, old)) = old.filter(|(provider, _)| provider == &item.active_provider.uuid) {
+					for event in transitions(&old, &item.active_provider.stati) {
+						if expected && event == LifecycleEvent::UnexpectedExit {
+							continue;
+						}
+						if settings.boolean(event.settings_key()) {
+							application.send_notification(
+								Some(&format!("{}-{}", event.settings_key(), item.uuid)),
+								&event.notification(item)
+							);
+						}
+					}
+				}
+			},
+			_ => {}
+		}
+	}
+}
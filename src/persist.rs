@@ -0,0 +1,86 @@
+use std::{cell::RefCell,
+          collections::HashMap,
+          path::{Path, PathBuf},
+          time::Duration};
+
+use gtk::glib;
+use serde_json::{Map, Value};
+
+/// How long changes are collected before they are written.
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+thread_local! {
+	/// saves waiting for their delay, by file
+	static PENDING: RefCell<HashMap<PathBuf, (glib::SourceId, fn() -> Map<String, Value>)>> =
+		RefCell::new(HashMap::new());
+}
+
+/// Location of a file of the frontend in the users data directory.
+pub fn path(name: &str) -> PathBuf {
+	glib::user_data_dir().join("gtopia").join(name)
+}
+
+/// Reads the JSON object stored in the file. Missing files are empty,
+/// unreadable ones are reported and treated as empty.
+pub fn load(path: &Path) -> Map<String, Value> {
+	let data = match std::fs::read(path) {
+		Ok(data) => data,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Map::new(),
+		Err(e) => {
+			eprintln!("Failed reading {}: {}", path.display(), e);
+			return Map::new();
+		}
+	};
+	match serde_json::from_slice::<Value>(&data) {
+		Ok(Value::Object(entries)) => entries,
+		Ok(_) => {
+			eprintln!("Ignoring malformed {}", path.display());
+			Map::new()
+		},
+		Err(e) => {
+			eprintln!("Failed parsing {}: {}", path.display(), e);
+			Map::new()
+		}
+	}
+}
+
+/// Writes the object aside first and moves it in place, so readers
+/// never see a half written file.
+pub fn save(path: &Path, entries: Map<String, Value>) {
+	let partial = path.with_extension("json.part");
+	let saved = path
+		.parent()
+		.map_or(Ok(()), std::fs::create_dir_all)
+		.and_then(|_| std::fs::write(&partial, Value::Object(entries).to_string()))
+		.and_then(|_| std::fs::rename(&partial, path));
+	if let Err(e) = saved {
+		eprintln!("Failed writing {}: {}", path.display(), e);
+	}
+}
+
+/// Saves what `entries` returns once there were no changes for
+/// `SAVE_DELAY`, so that bursts of changes are written once.
+pub fn schedule_save(path: PathBuf, entries: fn() -> Map<String, Value>) {
+	let previous = PENDING.with(|pending| pending.borrow_mut().remove(&path));
+	if let Some((source, _)) = previous {
+		glib::source_remove(source);
+	}
+	let source = glib::timeout_add_local_once(SAVE_DELAY, {
+		let path = path.clone();
+		move || {
+			PENDING.with(|pending| pending.borrow_mut().remove(&path));
+			save(&path, entries());
+		}
+	});
+	PENDING.with(|pending| pending.borrow_mut().insert(path, (source, entries)));
+}
+
+/// Writes every save still waiting for its delay, to be called before
+/// quitting.
+pub fn flush() {
+	let pending = PENDING.with(|pending| pending.take());
+	for (path, (source, entries)) in pending {
+		glib::source_remove(source);
+		save(&path, entries());
+	}
+}
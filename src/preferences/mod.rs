@@ -20,16 +20,24 @@ pub type ValueStore = Arc<RwLock<std::collections::HashMap<String, preferences::
 
 pub struct GtopiaPreferenceBuilder {
 	diag: preferences::PreferenceDiag,
-	values: ValueStore
+	values: ValueStore,
+	title: Option<String>
 }
 impl GtopiaPreferenceBuilder {
 	pub fn new(diag: preferences::PreferenceDiag, values: ValueStore) -> Self {
 		GtopiaPreferenceBuilder {
 			diag,
-			values
+			values,
+			title: None
 		}
 	}
 
+	/// Title of the window, telling what the preferences belong to.
+	pub fn title(mut self, title: &str) -> Self {
+		self.title = Some(title.to_owned());
+		self
+	}
+
 	fn build_entry(&self, value: &String, uuid: String, purpose: gtk::InputPurpose) -> gtk::Entry {
 		let buf = gtk::EntryBuffer::new(Some(&value));
 		let entry = gtk::Entry::builder().buffer(&buf).input_purpose(purpose).build();
@@ -321,6 +329,9 @@ impl GtopiaPreferenceBuilder {
 			.can_swipe_back(true)
 			.search_enabled(true)
 			.build();
+		if let Some(title) = &self.title {
+			win.set_title(Some(title));
+		}
 
 		for page in &self.diag.panes {
 			win.add(&self.build_page(page));
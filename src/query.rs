@@ -0,0 +1,445 @@
+use std::ops::Range;
+
+use utopia_common::library::LibraryItemStatus;
+
+use crate::{fuzzy, store::UtopiaItem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+	Status,
+	Provider,
+	Kind,
+	Tag
+}
+
+impl Key {
+	pub const ALL: [Key; 4] = [Key::Status, Key::Provider, Key::Kind, Key::Tag];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Status => "status",
+			Self::Provider => "provider",
+			Self::Kind => "kind",
+			Self::Tag => "tag"
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.iter().copied().find(|key| key.name() == name)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+	Running,
+	Closing,
+	Updatable,
+	Updating,
+	Installed
+}
+
+impl Status {
+	pub const ALL: [Status; 5] = [
+		Status::Running,
+		Status::Closing,
+		Status::Updatable,
+		Status::Updating,
+		Status::Installed
+	];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Running => "running",
+			Self::Closing => "closing",
+			Self::Updatable => "updatable",
+			Self::Updating => "updating",
+			Self::Installed => "installed"
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.iter().copied().find(|status| status.name() == name)
+	}
+
+	fn matches(&self, status: &LibraryItemStatus) -> bool {
+		matches!(
+			(self, status),
+			(Self::Running, LibraryItemStatus::Running(_)) |
+				(Self::Closing, LibraryItemStatus::Closing) |
+				(Self::Updatable, LibraryItemStatus::Updatable) |
+				(Self::Updating, LibraryItemStatus::Updating) |
+				(Self::Installed, LibraryItemStatus::Installed)
+		)
+	}
+
+	/// Whether the active provider reports this status for the item.
+	pub fn applies_to(&self, item: &UtopiaItem) -> bool {
+		item.details()
+			.active_provider
+			.stati
+			.iter()
+			.any(|status| self.matches(status))
+	}
+}
+
+/// A single `key:value` term. Values other than stati are
+/// [normalized](fuzzy::normalize).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+	Status(Status),
+	Provider(String),
+	Kind(String),
+	Tag(String)
+}
+
+impl Filter {
+	pub fn key(&self) -> Key {
+		match self {
+			Self::Status(_) => Key::Status,
+			Self::Provider(_) => Key::Provider,
+			Self::Kind(_) => Key::Kind,
+			Self::Tag(_) => Key::Tag
+		}
+	}
+
+	fn matches(&self, item: &UtopiaItem) -> bool {
+		match self {
+			Self::Status(status) => status.applies_to(item),
+			Self::Provider(provider) => item.details().providers.iter().any(|(uuid, iprovider)| {
+				&fuzzy::normalize(uuid) == provider || fuzzy::normalize(&iprovider.name).contains(provider.as_str())
+			}),
+			Self::Kind(kind) => fuzzy::normalize(&item.kind_name()).contains(kind.as_str()),
+			Self::Tag(tag) => crate::tags::tags(&item.uuid())
+				.iter()
+				.any(|other| &fuzzy::normalize(other) == tag)
+		}
+	}
+}
+
+/// A parsed search query: free text, combined with filters like
+/// `status:running`, `provider:steam` or `-tag:finished`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+	/// the free text, [normalized](fuzzy::normalize)
+	pub text: String,
+	/// filters, with whether they are negated
+	pub filters: Vec<(bool, Filter)>
+}
+
+impl Query {
+	/// Whether any filter looks at the key.
+	pub fn uses(&self, key: Key) -> bool {
+		self.filters.iter().any(|(_, filter)| filter.key() == key)
+	}
+
+	/// Whether the item passes all filters. The free text is ranked
+	/// separately.
+	pub fn matches(&self, item: &UtopiaItem) -> bool {
+		self.filters
+			.iter()
+			.all(|(negated, filter)| filter.matches(item) != *negated)
+	}
+}
+
+struct Token {
+	range: Range<usize>,
+	/// the token without quotes
+	text: String
+}
+
+/// Splits at whitespace, except inside double quotes. Unterminated quotes
+/// reach until the end.
+fn tokenize(input: &str) -> Vec<Token> {
+	let mut tokens = Vec::new();
+	let mut current: Option<Token> = None;
+	let mut quoted = false;
+	for (index, c) in input.char_indices() {
+		if c.is_whitespace() && !quoted {
+			tokens.extend(current.take());
+			continue;
+		}
+		let token = current.get_or_insert_with(|| Token {
+			range: index..index,
+			text: String::new()
+		});
+		token.range.end = index + c.len_utf8();
+		if c == '"' {
+			quoted = !quoted;
+		} else {
+			token.text.push(c);
+		}
+	}
+	tokens.extend(current);
+	tokens
+}
+
+/// Splits `-key:value` into its parts, if the token has that form at all.
+/// Free text like "Half-Life: Alyx" is left alone.
+fn split_filter(token: &str) -> Option<(bool, &str, &str)> {
+	let (negated, token) = match token.strip_prefix('-') {
+		Some(token) => (true, token),
+		None => (false, token)
+	};
+	let (key, value) = token.split_once(':')?;
+	if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+		return None;
+	}
+	Some((negated, key, value))
+}
+
+/// A message about a term of the query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notice {
+	/// the term is left out
+	Error(String),
+	/// the term is taken differently than it might look
+	Hint(String)
+}
+
+impl Notice {
+	pub fn message(&self) -> &str {
+		match self {
+			Self::Error(message) | Self::Hint(message) => message
+		}
+	}
+}
+
+/// Parses the query, along with notices about the terms that could not
+/// be understood. Unknown keys are searched as text, other terms that
+/// make no sense are left out; the rest of the query still applies.
+pub fn parse(input: &str) -> (Query, Vec<Notice>) {
+	let mut query = Query::default();
+	let mut text = Vec::new();
+	let mut notices = Vec::new();
+	for token in tokenize(input) {
+		let (negated, key, value) = match split_filter(&token.text) {
+			Some(filter) => filter,
+			None => {
+				text.push(token.text);
+				continue;
+			}
+		};
+		let key = match Key::from_name(&key.to_lowercase()) {
+			Some(key) => key,
+			// "Portal:" is more likely part of a name than a filter
+			None if value.is_empty() => {
+				text.push(token.text);
+				continue;
+			},
+			// so is "Re:Zero"
+			None => {
+				notices.push(Notice::Hint(format!("Unknown filter “{}:”, searching as text", key)));
+				text.push(token.text);
+				continue;
+			}
+		};
+		// still being typed
+		if value.is_empty() {
+			continue;
+		}
+		let value = fuzzy::normalize(value);
+		let filter = match key {
+			Key::Status => match Status::from_name(&value) {
+				Some(status) => Filter::Status(status),
+				None => {
+					let expected: Vec<&str> = Status::ALL.iter().map(Status::name).collect();
+					notices.push(Notice::Error(format!(
+						"Unknown status “{}”, expected one of {}",
+						value,
+						expected.join(", ")
+					)));
+					continue;
+				}
+			},
+			Key::Provider => Filter::Provider(value),
+			Key::Kind => Filter::Kind(value),
+			Key::Tag => Filter::Tag(value)
+		};
+		query.filters.push((negated, filter));
+	}
+	query.text = fuzzy::normalize(&text.join(" "));
+	(query, notices)
+}
+
+/// A completion of the last term of the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+	pub label: String,
+	/// the input with the completion applied
+	pub text: String
+}
+
+/// Completes the term at the end of the input: keys first, then their
+/// values, which `values` provides for each key.
+pub fn complete<F: Fn(Key) -> Vec<String>>(input: &str, values: F) -> Vec<Completion> {
+	let last = match tokenize(input).pop() {
+		// the last term is done once it's followed by a space
+		Some(token) if token.range.end == input.len() => token,
+		_ => return Vec::new()
+	};
+	let head = &input[..last.range.start];
+	let (negation, term) = match last.text.strip_prefix('-') {
+		Some(term) => ("-", term),
+		None => ("", last.text.as_str())
+	};
+
+	match term.split_once(':') {
+		None => {
+			let term = term.to_lowercase();
+			if term.is_empty() {
+				return Vec::new();
+			}
+			Key::ALL
+				.iter()
+				.filter(|key| key.name().starts_with(&term) && key.name() != term)
+				.map(|key| Completion {
+					label: format!("{}:", key.name()),
+					text: format!("{}{}{}:", head, negation, key.name())
+				})
+				.collect()
+		},
+		Some((key, value)) => {
+			let key = match Key::from_name(&key.to_lowercase()) {
+				Some(key) => key,
+				None => return Vec::new()
+			};
+			let value = fuzzy::normalize(value);
+			let mut candidates = match key {
+				Key::Status => Status::ALL.iter().map(|status| status.name().to_owned()).collect(),
+				key => values(key)
+			};
+			candidates.retain(|candidate| {
+				let candidate = fuzzy::normalize(candidate);
+				candidate.contains(&value) && candidate != value
+			});
+			candidates
+				.into_iter()
+				.map(|candidate| {
+					let quoted = if candidate.contains(char::is_whitespace) {
+						format!("\"{}\"", candidate)
+					} else {
+						candidate.clone()
+					};
+					Completion {
+						text: format!("{}{}{}:{} ", head, negation, key.name(), quoted),
+						label: candidate
+					}
+				})
+				.collect()
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn filters(input: &str) -> Vec<(bool, Filter)> {
+		let (query, notices) = parse(input);
+		assert_eq!(notices, Vec::new(), "{}", input);
+		query.filters
+	}
+
+	#[test]
+	fn parses_empty_input() {
+		assert_eq!(parse(""), (Query::default(), Vec::new()));
+		assert_eq!(parse("   "), (Query::default(), Vec::new()));
+	}
+
+	#[test]
+	fn normalizes_free_text() {
+		let (query, notices) = parse("Pokémon  Red");
+		assert_eq!(query.text, "pokemon red");
+		assert!(query.filters.is_empty());
+		assert!(notices.is_empty());
+	}
+
+	#[test]
+	fn parses_filters_among_text() {
+		let (query, notices) = parse("status:running zelda Provider:Steam");
+		assert_eq!(query.text, "zelda");
+		assert_eq!(query.filters, vec![
+			(false, Filter::Status(Status::Running)),
+			(false, Filter::Provider(String::from("steam")))
+		]);
+		assert!(notices.is_empty());
+	}
+
+	#[test]
+	fn parses_negated_and_quoted_filters() {
+		assert_eq!(filters("-tag:finished"), vec![(true, Filter::Tag(String::from("finished")))]);
+		assert_eq!(filters("tag:\"to play\""), vec![(false, Filter::Tag(String::from("to play")))]);
+		// unterminated quotes reach until the end
+		assert_eq!(filters("tag:\"to play"), vec![(false, Filter::Tag(String::from("to play")))]);
+		assert_eq!(filters("kind:Visual-Novel"), vec![(false, Filter::Kind(String::from("visual novel")))]);
+		assert_eq!(filters("STATUS:Updatable"), vec![(false, Filter::Status(Status::Updatable))]);
+	}
+
+	#[test]
+	fn keeps_names_with_colons_as_text() {
+		let (query, notices) = parse("Half-Life: Alyx");
+		assert_eq!(query.text, "half life alyx");
+		assert!(query.filters.is_empty());
+		assert!(notices.is_empty());
+
+		let (query, notices) = parse("Portal:");
+		assert_eq!(query.text, "portal");
+		assert!(notices.is_empty());
+	}
+
+	#[test]
+	fn hints_at_unknown_keys() {
+		let (query, notices) = parse("Re:Zero");
+		assert_eq!(query.text, "re zero");
+		assert!(query.filters.is_empty());
+		assert_eq!(notices, vec![Notice::Hint(String::from("Unknown filter “Re:”, searching as text"))]);
+	}
+
+	#[test]
+	fn leaves_out_unknown_stati() {
+		let (query, notices) = parse("status:broken zelda");
+		assert_eq!(query.text, "zelda");
+		assert!(query.filters.is_empty());
+		assert_eq!(notices, vec![Notice::Error(String::from(
+			"Unknown status “broken”, expected one of running, closing, updatable, updating, installed"
+		))]);
+	}
+
+	#[test]
+	fn ignores_filters_still_being_typed() {
+		assert_eq!(parse("status:"), (Query::default(), Vec::new()));
+		assert_eq!(parse("-tag:"), (Query::default(), Vec::new()));
+	}
+
+	#[test]
+	fn survives_stray_punctuation() {
+		for input in &["-", ":", "-:", "\"", "::", "-:value", "a:b:c"] {
+			let (query, _) = parse(input);
+			assert!(query.filters.is_empty(), "{}", input);
+		}
+	}
+
+	#[test]
+	fn completes_keys_and_values() {
+		let none = |_: Key| Vec::<String>::new();
+		assert_eq!(complete("sta", none), vec![Completion {
+			label: String::from("status:"),
+			text: String::from("status:")
+		}]);
+		assert_eq!(complete("zelda -ta", none), vec![Completion {
+			label: String::from("tag:"),
+			text: String::from("zelda -tag:")
+		}]);
+		assert_eq!(complete("status:run", none), vec![Completion {
+			label: String::from("running"),
+			text: String::from("status:running ")
+		}]);
+		assert_eq!(complete("tag:to", |_| vec![String::from("to play")]), vec![Completion {
+			label: String::from("to play"),
+			text: String::from("tag:\"to play\" ")
+		}]);
+		// nothing to complete after a finished term
+		assert_eq!(complete("zelda ", none), Vec::new());
+		assert_eq!(complete("", none), Vec::new());
+	}
+}
@@ -0,0 +1,82 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+/// What the sidebar shows about an integration.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IntegrationInfo {
+	pub uuid: String,
+	pub name: String,
+	pub icon: Option<String>,
+	/// games it provides
+	pub count: u32,
+	/// games running with it as active provider
+	pub running: u32,
+	/// games it has updates for
+	pub updatable: u32
+}
+
+mod imp {
+	use glib::subclass::Signal;
+	use once_cell::sync::Lazy;
+
+	use super::*;
+
+	#[derive(Debug, Default)]
+	pub struct UtopiaIntegration {
+		pub info: std::cell::RefCell<IntegrationInfo>
+	}
+
+	#[glib::object_subclass]
+	impl ObjectSubclass for UtopiaIntegration {
+		type ParentType = glib::Object;
+		type Type = super::UtopiaIntegration;
+
+		const NAME: &'static str = "UtopiaIntegration";
+	}
+
+	impl ObjectImpl for UtopiaIntegration {
+		fn signals() -> &'static [Signal] {
+			static SIGNALS: Lazy<Vec<Signal>> =
+				Lazy::new(|| vec![Signal::builder("changed", &[], <()>::static_type().into()).build()]);
+			SIGNALS.as_ref()
+		}
+	}
+}
+
+glib::wrapper! {
+	/// An integration providing games of the library, as kept by the
+	/// [`ItemStore`](super::ItemStore). Emits `changed` whenever its
+	/// counts change.
+	pub struct UtopiaIntegration(ObjectSubclass<imp::UtopiaIntegration>);
+}
+
+impl UtopiaIntegration {
+	pub fn new(info: IntegrationInfo) -> Self {
+		let integration: Self = glib::Object::new(&[]).expect("Failed to create UtopiaIntegration");
+		imp::UtopiaIntegration::from_instance(&integration).info.replace(info);
+		integration
+	}
+
+	pub fn info(&self) -> std::cell::Ref<IntegrationInfo> {
+		imp::UtopiaIntegration::from_instance(self).info.borrow()
+	}
+
+	pub fn uuid(&self) -> String {
+		self.info().uuid.clone()
+	}
+
+	pub fn replace(&self, info: IntegrationInfo) {
+		let self_ = imp::UtopiaIntegration::from_instance(self);
+		if *self_.info.borrow() != info {
+			self_.info.replace(info);
+			self.emit_by_name("changed", &[]).unwrap();
+		}
+	}
+
+	pub fn connect_changed<F: Fn(&Self) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+		self.connect_local("changed", false, move |values| {
+			f(&values[0].get::<Self>().unwrap());
+			None
+		})
+		.unwrap()
+	}
+}
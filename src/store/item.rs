@@ -0,0 +1,220 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use utopia_common::library::{LibraryItemFrontend, LibraryItemFrontendDetails, LibraryItemStatus};
+
+mod imp {
+	use glib::subclass::Signal;
+	use once_cell::sync::Lazy;
+
+	use super::*;
+
+	#[derive(Debug, Default)]
+	pub struct UtopiaItem {
+		pub details: std::cell::RefCell<Option<LibraryItemFrontendDetails>>,
+		pub collation_key: std::cell::RefCell<Option<String>>,
+		pub provider_collation_key: std::cell::RefCell<Option<String>>,
+		/// last search text with the score of the item for it
+		pub search_score: std::cell::RefCell<Option<(String, Option<f64>)>>
+	}
+
+	#[glib::object_subclass]
+	impl ObjectSubclass for UtopiaItem {
+		type ParentType = glib::Object;
+		type Type = super::UtopiaItem;
+
+		const NAME: &'static str = "UtopiaItem";
+	}
+
+	impl ObjectImpl for UtopiaItem {
+		fn signals() -> &'static [Signal] {
+			static SIGNALS: Lazy<Vec<Signal>> =
+				Lazy::new(|| vec![Signal::builder("changed", &[], <()>::static_type().into()).build()]);
+			SIGNALS.as_ref()
+		}
+	}
+}
+
+/// Which parts of an item an update touched, so that filters and sort
+/// orders only reconsider it if they look at them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ItemChanges {
+	pub name: bool,
+	pub kind: bool,
+	pub providers: bool,
+	/// the active provider or its stati
+	pub status: bool
+}
+
+impl ItemChanges {
+	pub fn any(&self) -> bool {
+		self.name || self.kind || self.providers || self.status
+	}
+}
+
+/// The parts of the details that are compared for [`ItemChanges`].
+#[derive(PartialEq)]
+struct Facets {
+	name: String,
+	kind: String,
+	providers: Vec<(String, String)>,
+	active_provider: String,
+	stati: Vec<std::mem::Discriminant<LibraryItemStatus>>
+}
+
+impl Facets {
+	fn of(details: &LibraryItemFrontendDetails) -> Self {
+		let mut providers: Vec<(String, String)> = details
+			.providers
+			.iter()
+			.map(|(uuid, provider)| (uuid.clone(), provider.name.clone()))
+			.collect();
+		providers.sort();
+		Self {
+			name: details.name.clone(),
+			kind: format!("{:?}", details.kind),
+			providers,
+			active_provider: details.active_provider.uuid.clone(),
+			stati: details.active_provider.stati.iter().map(std::mem::discriminant).collect()
+		}
+	}
+
+	fn changes(&self, new: &Self) -> ItemChanges {
+		ItemChanges {
+			name: self.name != new.name,
+			kind: self.kind != new.kind,
+			providers: self.providers != new.providers,
+			status: self.active_provider != new.active_provider || self.stati != new.stati
+		}
+	}
+}
+
+/// Whether any of the stati says the game runs.
+pub fn is_running(stati: &[LibraryItemStatus]) -> bool {
+	stati.iter().any(|status| matches!(status, LibraryItemStatus::Running(_)))
+}
+
+glib::wrapper! {
+	/// A game of the library, as kept by the [`ItemStore`](super::ItemStore).
+	/// Emits `changed` whenever the daemon updates it.
+	pub struct UtopiaItem(ObjectSubclass<imp::UtopiaItem>);
+}
+
+impl UtopiaItem {
+	pub fn new(details: LibraryItemFrontendDetails) -> Self {
+		let item: Self = glib::Object::new(&[]).expect("Failed to create UtopiaItem");
+		imp::UtopiaItem::from_instance(&item).details.replace(Some(details));
+		item
+	}
+
+	pub fn details(&self) -> std::cell::Ref<LibraryItemFrontendDetails> {
+		let self_ = imp::UtopiaItem::from_instance(self);
+		std::cell::Ref::map(self_.details.borrow(), |details| details.as_ref().unwrap())
+	}
+
+	pub fn uuid(&self) -> String {
+		self.details().uuid.clone()
+	}
+
+	pub fn name(&self) -> String {
+		self.details().name.clone()
+	}
+
+	/// Locale aware sort key of the name, ordering numbers naturally.
+	pub fn collation_key(&self) -> std::cell::Ref<str> {
+		let self_ = imp::UtopiaItem::from_instance(self);
+		if self_.collation_key.borrow().is_none() {
+			let key = glib::utf8_collate_key_for_filename(&self.name(), -1);
+			self_.collation_key.replace(Some(key.to_string()));
+		}
+		std::cell::Ref::map(self_.collation_key.borrow(), |key| key.as_deref().unwrap())
+	}
+
+	/// Locale aware sort key of the active providers name.
+	pub fn provider_collation_key(&self) -> std::cell::Ref<str> {
+		let self_ = imp::UtopiaItem::from_instance(self);
+		if self_.provider_collation_key.borrow().is_none() {
+			let key = glib::utf8_collate_key(&self.details().active_provider.name, -1);
+			self_.provider_collation_key.replace(Some(key.to_string()));
+		}
+		std::cell::Ref::map(self_.provider_collation_key.borrow(), |key| key.as_deref().unwrap())
+	}
+
+	/// Score of the item for the search text, computed by `score` only
+	/// once for every text and version of the item.
+	pub fn search_score<F: FnOnce(&Self) -> Option<f64>>(&self, text: &str, score: F) -> Option<f64> {
+		let self_ = imp::UtopiaItem::from_instance(self);
+		if let Some((cached, score)) = self_.search_score.borrow().as_ref() {
+			if cached == text {
+				return *score;
+			}
+		}
+		let computed = score(self);
+		self_.search_score.replace(Some((text.to_owned(), computed)));
+		computed
+	}
+
+	pub fn kind_name(&self) -> String {
+		format!("{:?}", self.details().kind)
+	}
+
+	pub fn is_running(&self) -> bool {
+		is_running(&self.details().active_provider.stati)
+	}
+
+	pub fn provider(&self, provider: &str) -> bool {
+		self.details().providers.keys().any(|iprovider| iprovider == provider)
+	}
+
+	/// Label for the last status of the active provider.
+	pub fn status(&self) -> Option<&'static str> {
+		self.details().active_provider.stati.last().map(|status| match status {
+			LibraryItemStatus::Running(_pid) => "Running",
+			LibraryItemStatus::Closing => "Closing",
+			LibraryItemStatus::Updatable => "Update available",
+			LibraryItemStatus::Updating => "Updating",
+			LibraryItemStatus::Installed => "Installed"
+		})
+	}
+
+	pub fn replace(&self, details: LibraryItemFrontendDetails) -> ItemChanges {
+		let self_ = imp::UtopiaItem::from_instance(self);
+		let changes = Facets::of(&self.details()).changes(&Facets::of(&details));
+		self_.details.replace(Some(details));
+		crate::artwork::forget(&self.uuid());
+		self_.collation_key.replace(None);
+		self_.provider_collation_key.replace(None);
+		self_.search_score.replace(None);
+		self.emit_changed();
+		changes
+	}
+
+	pub fn update(&self, item: LibraryItemFrontend) -> ItemChanges {
+		let changes = {
+			let self_ = imp::UtopiaItem::from_instance(self);
+			let mut details = self_.details.borrow_mut();
+			let details = details.as_mut().unwrap();
+			let old = Facets::of(details);
+			details.name = item.name;
+			details.kind = item.kind;
+			details.active_provider = item.active_provider;
+			details.providers = item.providers;
+			self_.collation_key.replace(None);
+			self_.provider_collation_key.replace(None);
+			self_.search_score.replace(None);
+			old.changes(&Facets::of(details))
+		};
+		self.emit_changed();
+		changes
+	}
+
+	pub fn emit_changed(&self) {
+		self.emit_by_name("changed", &[]).unwrap();
+	}
+
+	pub fn connect_changed<F: Fn(&Self) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+		self.connect_local("changed", false, move |values| {
+			f(&values[0].get::<Self>().unwrap());
+			None
+		})
+		.unwrap()
+	}
+}
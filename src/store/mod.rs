@@ -0,0 +1,250 @@
+pub mod integration;
+pub mod item;
+
+use std::{cell::{Cell, RefCell},
+          collections::{HashMap, HashSet},
+          rc::Rc};
+
+use gtk::{gio, glib, prelude::*};
+use utopia_common::library::{LibraryItemFrontend, LibraryItemFrontendDetails};
+
+pub use integration::{IntegrationInfo, UtopiaIntegration};
+pub use item::{is_running, ItemChanges, UtopiaItem};
+
+/// Every item of the library, shared by all widgets. Widgets keep uuids
+/// or references to the items and subscribe to their `changed` signal
+/// rather than copying them.
+pub struct ItemStore {
+	items: RefCell<HashMap<String, UtopiaItem>>,
+	model: gio::ListStore,
+	integrations: gio::ListStore,
+	recount_pending: Rc<Cell<bool>>,
+	listeners: RefCell<Vec<Rc<dyn Fn(&UtopiaItem, ItemChanges)>>>
+}
+
+impl std::fmt::Debug for ItemStore {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ItemStore")
+			.field("items", &self.items)
+			.field("model", &self.model)
+			.field("integrations", &self.integrations)
+			.finish_non_exhaustive()
+	}
+}
+
+impl Default for ItemStore {
+	fn default() -> Self {
+		Self {
+			items: RefCell::new(HashMap::new()),
+			model: gio::ListStore::new(UtopiaItem::static_type()),
+			integrations: gio::ListStore::new(UtopiaIntegration::static_type()),
+			recount_pending: Rc::new(Cell::new(false)),
+			listeners: RefCell::new(Vec::new())
+		}
+	}
+}
+
+impl ItemStore {
+	/// All items as list model, in insertion order.
+	pub fn model(&self) -> &gio::ListStore {
+		&self.model
+	}
+
+	/// Every integration providing at least one item, in the order they
+	/// showed up.
+	pub fn integrations(&self) -> &gio::ListStore {
+		&self.integrations
+	}
+
+	pub fn integration(&self, uuid: &str) -> Option<UtopiaIntegration> {
+		(0..self.integrations.n_items())
+			.filter_map(|position| self.integrations.item(position))
+			.filter_map(|integration| integration.downcast::<UtopiaIntegration>().ok())
+			.find(|integration| integration.uuid() == uuid)
+	}
+
+	pub fn item(&self, uuid: &str) -> Option<UtopiaItem> {
+		self.items.borrow().get(uuid).cloned()
+	}
+
+	/// Uuid and name of every item.
+	pub fn items(&self) -> Vec<(String, String)> {
+		self.items
+			.borrow()
+			.iter()
+			.map(|(uuid, item)| (uuid.clone(), item.name()))
+			.collect()
+	}
+
+	/// Names of all providers of the library, sorted and without
+	/// duplicates.
+	pub fn provider_names(&self) -> Vec<String> {
+		let mut names: Vec<String> = self
+			.items
+			.borrow()
+			.values()
+			.flat_map(|item| {
+				item.details()
+					.providers
+					.values()
+					.map(|provider| provider.name.clone())
+					.collect::<Vec<_>>()
+			})
+			.collect();
+		names.sort_by_cached_key(|name| glib::utf8_collate_key(name, -1));
+		names.dedup();
+		names
+	}
+
+	/// Kinds of the items in the library, sorted and without duplicates.
+	pub fn kind_names(&self) -> Vec<String> {
+		let mut kinds: Vec<String> = self.items.borrow().values().map(UtopiaItem::kind_name).collect();
+		kinds.sort();
+		kinds.dedup();
+		kinds
+	}
+
+	/// Takes over the library as sent by the daemon, dropping items
+	/// that are no longer part of it.
+	pub fn replace_all(&self, library: Vec<LibraryItemFrontendDetails>) {
+		let uuids: HashSet<String> = library.iter().map(|details| details.uuid.clone()).collect();
+		for details in library {
+			self.insert(details);
+		}
+		let removed = {
+			let mut items = self.items.borrow_mut();
+			let before = items.len();
+			items.retain(|uuid, _| uuids.contains(uuid));
+			items.len() != before
+		};
+		if !removed {
+			return;
+		}
+		for position in (0..self.model.n_items()).rev() {
+			let stale = self
+				.model
+				.item(position)
+				.and_then(|item| item.downcast::<UtopiaItem>().ok())
+				.map_or(true, |item| !uuids.contains(&item.uuid()));
+			if stale {
+				self.model.remove(position);
+			}
+		}
+		self.schedule_recount();
+	}
+
+	pub fn insert(&self, details: LibraryItemFrontendDetails) {
+		if let Some(existing) = self.item(&details.uuid) {
+			let changes = existing.replace(details);
+			self.item_changed(&existing, changes);
+			return;
+		}
+		let uuid = details.uuid.clone();
+		crate::history::record_added(&uuid);
+		let item = UtopiaItem::new(details);
+		self.model.append(&item);
+		self.items.borrow_mut().insert(uuid, item);
+		self.schedule_recount();
+	}
+
+	pub fn update(&self, update: LibraryItemFrontend) -> Option<UtopiaItem> {
+		let item = self.item(&update.uuid)?;
+		let was_running = item.is_running();
+		let changes = item.update(update);
+		if !was_running && item.is_running() {
+			crate::history::record_played(&item.uuid());
+		}
+		self.item_changed(&item, changes);
+		Some(item)
+	}
+
+	/// Runs `f` whenever an item got updated, with what changed. Widgets
+	/// showing the item follow its `changed` signal instead.
+	pub fn connect_item_changed<F: Fn(&UtopiaItem, ItemChanges) + 'static>(&self, f: F) {
+		self.listeners.borrow_mut().push(Rc::new(f));
+	}
+
+	fn item_changed(&self, item: &UtopiaItem, changes: ItemChanges) {
+		if !changes.any() {
+			return;
+		}
+		let listeners = self.listeners.borrow().clone();
+		for listener in listeners {
+			listener(item, changes);
+		}
+		self.schedule_recount();
+	}
+
+	/// Recounts the integrations once the main loop is idle, so that
+	/// loading a large library does not rescan it for every item.
+	fn schedule_recount(&self) {
+		if self.recount_pending.replace(true) {
+			return;
+		}
+		let (model, integrations) = (self.model.clone(), self.integrations.clone());
+		let pending = self.recount_pending.clone();
+		glib::idle_add_local_once(move || {
+			pending.set(false);
+			refresh_integrations(&model, &integrations);
+		});
+	}
+}
+
+/// Recounts the items of every integration, adding the ones that
+/// showed up and removing the ones no item refers to anymore.
+fn refresh_integrations(model: &gio::ListStore, integrations: &gio::ListStore) {
+	let mut infos: Vec<IntegrationInfo> = Vec::new();
+	let mut indices: HashMap<String, usize> = HashMap::new();
+	for position in 0..model.n_items() {
+		let item = match model.item(position).and_then(|item| item.downcast::<UtopiaItem>().ok()) {
+			Some(item) => item,
+			None => continue
+		};
+		let running = crate::query::Status::Running.applies_to(&item);
+		let updatable = crate::query::Status::Updatable.applies_to(&item);
+		let details = item.details();
+		for (uuid, provider) in &details.providers {
+			let index = *indices.entry(uuid.clone()).or_insert_with(|| {
+				infos.push(IntegrationInfo {
+					uuid: uuid.clone(),
+					name: provider.name.clone(),
+					icon: provider.icon.clone(),
+					..IntegrationInfo::default()
+				});
+				infos.len() - 1
+			});
+			let info = &mut infos[index];
+			info.count += 1;
+			if &details.active_provider.uuid == uuid {
+				info.running += running as u32;
+				info.updatable += updatable as u32;
+			}
+		}
+	}
+
+	// infos not taken by an existing integration are new
+	let mut infos: Vec<Option<IntegrationInfo>> = infos.into_iter().map(Some).collect();
+	for position in (0..integrations.n_items()).rev() {
+		let integration = match integrations
+			.item(position)
+			.and_then(|integration| integration.downcast::<UtopiaIntegration>().ok())
+		{
+			Some(integration) => integration,
+			None => continue
+		};
+		let uuid = integration.uuid();
+		match indices.get(&uuid).and_then(|index| infos[*index].take()) {
+			Some(info) => integration.replace(info),
+			None => integrations.remove(position)
+		}
+	}
+	// all at once, so listeners see every new integration together
+	let added: Vec<glib::Object> = infos
+		.into_iter()
+		.flatten()
+		.map(|info| UtopiaIntegration::new(info).upcast())
+		.collect();
+	if !added.is_empty() {
+		integrations.splice(integrations.n_items(), 0, &added);
+	}
+}
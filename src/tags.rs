@@ -0,0 +1,80 @@
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, rc::Rc};
+
+use gtk::glib;
+
+thread_local! {
+	static TAGS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(load());
+	static LISTENERS: RefCell<Vec<Rc<dyn Fn(&str)>>> = RefCell::new(Vec::new());
+}
+
+fn path() -> PathBuf {
+	crate::persist::path("tags.json")
+}
+
+fn load() -> HashMap<String, Vec<String>> {
+	crate::persist::load(&path())
+		.into_iter()
+		.filter_map(|(uuid, tags)| {
+			let tags: Vec<String> = tags
+				.as_array()?
+				.iter()
+				.filter_map(|tag| tag.as_str().map(str::to_owned))
+				.collect();
+			Some((uuid, tags))
+		})
+		.collect()
+}
+
+fn entries() -> serde_json::Map<String, serde_json::Value> {
+	TAGS.with(|tags| {
+		tags.borrow()
+			.iter()
+			.map(|(uuid, tags)| (uuid.clone(), serde_json::json!(tags)))
+			.collect()
+	})
+}
+
+/// Runs `f` with the uuid of the item whenever its tags change.
+pub fn connect_changed<F: Fn(&str) + 'static>(f: F) {
+	LISTENERS.with(|listeners| listeners.borrow_mut().push(Rc::new(f)));
+}
+
+fn notify(uuid: &str) {
+	let listeners = LISTENERS.with(|listeners| listeners.borrow().clone());
+	for listener in listeners {
+		listener(uuid);
+	}
+}
+
+pub fn tags(uuid: &str) -> Vec<String> {
+	TAGS.with(|tags| tags.borrow().get(uuid).cloned().unwrap_or_default())
+}
+
+/// Every tag in use, sorted and without duplicates.
+pub fn all() -> Vec<String> {
+	let mut all: Vec<String> = TAGS.with(|tags| tags.borrow().values().flatten().cloned().collect());
+	all.sort_by_cached_key(|tag| glib::utf8_collate_key(tag, -1));
+	all.dedup();
+	all
+}
+
+/// Replaces the tags of the item. Tags are trimmed, empty and repeated
+/// ones dropped.
+pub fn set(uuid: &str, new: &[String]) {
+	let mut cleaned: Vec<String> = Vec::with_capacity(new.len());
+	for tag in new.iter().map(|tag| tag.trim()) {
+		if !tag.is_empty() && !cleaned.iter().any(|other| other == tag) {
+			cleaned.push(tag.to_owned());
+		}
+	}
+	TAGS.with(|tags| {
+		let mut tags = tags.borrow_mut();
+		if cleaned.is_empty() {
+			tags.remove(uuid);
+		} else {
+			tags.insert(uuid.to_owned(), cleaned);
+		}
+	});
+	crate::persist::schedule_save(path(), entries);
+	notify(uuid);
+}
@@ -32,8 +32,9 @@ pub enum UtopiaRequest {
 	TriggerKill(utopia::library::LibraryItemProviderQuitActions),
 	// uuid of game, uuid of provider
 	TriggerProviderUpdate(String, String),
-	// uuid of provider, uuid of game
-	TriggerPreferenceDiag(String, String),
+	// uuid of provider (unused with DiagType::Core), scope of the
+	// preferences
+	TriggerPreferenceDiag(String, utopia::library::preferences::DiagType),
 	SendUpdatedPreferences(
 		(String, utopia::library::preferences::DiagType),
 		std::collections::HashMap<std::string::String, utopia::library::preferences::FieldType>
@@ -184,12 +185,11 @@ impl UtopiaEvents {
 										//tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
 										socket.write_s(&serde_json::to_vec(&library_reqw).unwrap()).await.unwrap();
 									},
-									UtopiaRequest::TriggerPreferenceDiag(provider, uuid) => {
+									UtopiaRequest::TriggerPreferenceDiag(provider, scope) => {
 										let library_reqw = utopia::FrontendEvent {
 											version: String::from("0.0.0"),
 											uuid: Some(String::from(crate::config::APP_ID)),
-											action: utopia::FrontendActions::RequestPreferenceDiag(provider,
-												utopia_common::library::preferences::DiagType::Item(uuid))
+											action: utopia::FrontendActions::RequestPreferenceDiag(provider, scope)
 										};
 										socket.write_s(&serde_json::to_vec(&library_reqw).unwrap()).await.unwrap();
 									},
@@ -236,19 +236,22 @@ pub fn handle_event(
 		},
 		UtopiaMessage::RefreshGameLibrary(library) => {
 			//println!("Library: {:#?}", library);
-			for item in library {
-				//channel.try_send(UtopiaRequest::GetGameDetails(item.uuid)).
-				// unwrap(); println!("Item: {:?}", item);
-
-				window.new_item(item);
-			}
+			window.set_library(library);
 		},
 		UtopiaMessage::UpdateGame(item) => {
 			window.update_item(item);
 		},
 		UtopiaMessage::OpenPrefDiag(ptype, diag) => {
 			let values: crate::preferences::ValueStore = Arc::new(RwLock::new(std::collections::HashMap::new()));
-			let pref = crate::preferences::GtopiaPreferenceBuilder::new(diag, values.clone());
+			let title = match &ptype.1 {
+				utopia::library::preferences::DiagType::Item(uuid) => window.item_name(uuid),
+				utopia::library::preferences::DiagType::Provider => window.integration_name(&ptype.0),
+				utopia::library::preferences::DiagType::Core => Some(String::from("µtopia"))
+			};
+			let mut pref = crate::preferences::GtopiaPreferenceBuilder::new(diag, values.clone());
+			if let Some(title) = title {
+				pref = pref.title(&format!("{} Preferences", title));
+			}
 			let (prefdiag, save) = pref.build();
 			prefdiag.set_transient_for(Some(&window));
 			prefdiag.set_modal(true);
@@ -0,0 +1,83 @@
+pub const SCHEME: &str = "gtopia";
+
+/// Deep links of the form `gtopia://game/<uuid>` and
+/// `gtopia://launch/<uuid>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UtopiaUri {
+	Game(String),
+	Launch(String)
+}
+
+impl UtopiaUri {
+	pub fn parse(uri: &str) -> Option<Self> {
+		let rest = uri.strip_prefix(SCHEME)?.strip_prefix("://")?;
+		let rest = rest.split(|c| c == '?' || c == '#').next().unwrap_or("");
+		let (action, uuid) = rest.trim_end_matches('/').split_once('/')?;
+		if uuid.is_empty() || uuid.contains('/') {
+			return None;
+		}
+		match action {
+			"game" => Some(Self::Game(uuid.to_owned())),
+			"launch" => Some(Self::Launch(uuid.to_owned())),
+			_ => None
+		}
+	}
+
+	pub fn uuid(&self) -> &str {
+		match self {
+			Self::Game(uuid) | Self::Launch(uuid) => uuid
+		}
+	}
+
+	pub fn to_uri(&self) -> String {
+		match self {
+			Self::Game(uuid) => format!("{}://game/{}", SCHEME, uuid),
+			Self::Launch(uuid) => format!("{}://launch/{}", SCHEME, uuid)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_game_and_launch_links() {
+		assert_eq!(UtopiaUri::parse("gtopia://game/1234-abcd"), Some(UtopiaUri::Game(String::from("1234-abcd"))));
+		assert_eq!(UtopiaUri::parse("gtopia://launch/1234-abcd"), Some(UtopiaUri::Launch(String::from("1234-abcd"))));
+	}
+
+	#[test]
+	fn ignores_trailing_slash_query_and_fragment() {
+		let game = Some(UtopiaUri::Game(String::from("abc")));
+		assert_eq!(UtopiaUri::parse("gtopia://game/abc/"), game);
+		assert_eq!(UtopiaUri::parse("gtopia://game/abc?from=shell"), game);
+		assert_eq!(UtopiaUri::parse("gtopia://game/abc#top"), game);
+	}
+
+	#[test]
+	fn rejects_malformed_links() {
+		for uri in &[
+			"",
+			"gtopia",
+			"gtopia://",
+			"gtopia:game/abc",
+			"http://game/abc",
+			"gtopia://game",
+			"gtopia://game/",
+			"gtopia://game/?uuid=abc",
+			"gtopia://game/abc/def",
+			"gtopia://show/abc",
+			"gtopia:///abc"
+		] {
+			assert_eq!(UtopiaUri::parse(uri), None, "{}", uri);
+		}
+	}
+
+	#[test]
+	fn round_trips() {
+		for uri in &[UtopiaUri::Game(String::from("abc")), UtopiaUri::Launch(String::from("def"))] {
+			assert_eq!(UtopiaUri::parse(&uri.to_uri()).as_ref(), Some(uri));
+		}
+	}
+}
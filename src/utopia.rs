@@ -8,7 +8,10 @@ use gtk::{gio,
           CompositeTemplate};
 use libadwaita::{ApplicationWindow, NavigationDirection};
 
-use crate::{grid::UtopiaGrid, integration_item::UtopiaIntegrationItem};
+use crate::{grid::UtopiaGrid,
+            integration_item::UtopiaIntegrationItem,
+            integrations::IntegrationLayout,
+            store::{ItemStore, UtopiaIntegration}};
 
 #[derive(Debug, PartialEq)]
 pub enum LeafletFoci {
@@ -24,15 +27,19 @@ impl Default for LeafletFoci {
 
 pub mod imp {
 	use libadwaita::{subclass::prelude::*, ApplicationWindow, Leaflet};
-	use gtk::{Box, Button, ListBox, Revealer, SearchEntry, ToggleButton};
+	use gtk::{Box, Button, Label, ListBox, Revealer, SearchEntry, ToggleButton};
 
 	use super::*;
 	#[derive(Debug, Default, CompositeTemplate)]
 	#[template(resource = "/dev/sp1rit/Utopia/ui/window.ui")]
 	pub struct UtopiaWindow {
-		pub active_integration: Rc<RefCell<Option<glib::GString>>>,
-		pub integrations: RefCell<Vec<String>>,
+		/// integrations to show the games of, all if empty
+		pub active_integrations: Rc<RefCell<Vec<String>>>,
+		pub syncing_selection: std::cell::Cell<bool>,
+		pub settings: once_cell::unsync::OnceCell<gio::Settings>,
+		pub layout: IntegrationLayout,
 		pub widgetmap: Rc<RefCell<HashMap<glib::GString, UtopiaGrid>>>,
+		pub store: Rc<ItemStore>,
 
 		pub lfoci: Rc<RefCell<LeafletFoci>>,
 
@@ -50,10 +57,17 @@ pub mod imp {
 		pub search_revealer: TemplateChild<Revealer>,
 		#[template_child]
 		pub search: TemplateChild<SearchEntry>,
+		#[template_child]
+		pub search_error: TemplateChild<Label>,
+		pub completions: once_cell::unsync::OnceCell<gtk::Popover>,
+		/// what the rows of the completions fill in, by index
+		pub completion_items: Rc<RefCell<Vec<crate::query::Completion>>>,
 
 		#[template_child]
 		pub sidebar: TemplateChild<Box>,
 		#[template_child]
+		pub all_list: TemplateChild<ListBox>,
+		#[template_child]
 		pub module: TemplateChild<ListBox>,
 
 		#[template_child]
@@ -97,6 +111,12 @@ pub mod imp {
 
 			self.parent_constructed(obj);
 		}
+
+		fn dispose(&self, _obj: &Self::Type) {
+			if let Some(completions) = self.completions.get() {
+				completions.unparent();
+			}
+		}
 	}
 
 	impl WidgetImpl for UtopiaWindow {}
@@ -162,7 +182,9 @@ impl UtopiaWindow {
 			.search
 			.connect_search_changed(clone!(@weak self as utopia => move |_| {
 				utopia.update_filter();
+				utopia.update_completions();
 			}));
+		self.setup_completions();
 		self_
 			.search_btn
 			.bind_property("active", &self_.search_revealer.get(), "reveal-child")
@@ -179,6 +201,103 @@ impl UtopiaWindow {
 		}));
 	}
 
+	/// Suggests filter keys and their values below the search entry, for
+	/// the term that is being typed.
+	fn setup_completions(&self) {
+		let self_ = imp::UtopiaWindow::from_instance(self);
+		let search = self_.search.get();
+		let list = gtk::ListBox::new();
+		list.set_selection_mode(gtk::SelectionMode::Browse);
+		let items = self_.completion_items.clone();
+		list.connect_row_activated(clone!(@weak search => move |_, row| {
+			let text = match items.borrow().get(row.index() as usize) {
+				Some(completion) => completion.text.clone(),
+				None => return
+			};
+			search.set_text(&text);
+			search.grab_focus();
+			search.set_position(-1);
+		}));
+
+		let completions = gtk::PopoverBuilder::new()
+			.autohide(false)
+			.has_arrow(false)
+			.position(gtk::PositionType::Bottom)
+			.halign(gtk::Align::Start)
+			.child(&list)
+			.build();
+		completions.set_parent(&search);
+
+		// the popover does not take the focus, move there explicitly
+		let keys = gtk::EventControllerKey::new();
+		keys.connect_key_pressed(clone!(@weak list, @weak completions => @default-return gtk::Inhibit(false), move |_, key, _, _| {
+			if !completions.is_visible() {
+				return gtk::Inhibit(false);
+			}
+			let first = match list.row_at_index(0) {
+				Some(row) => row,
+				None => return gtk::Inhibit(false)
+			};
+			if key == gtk::gdk::keys::constants::Down {
+				first.grab_focus();
+				gtk::Inhibit(true)
+			} else if key == gtk::gdk::keys::constants::Tab {
+				first.activate();
+				gtk::Inhibit(true)
+			} else {
+				gtk::Inhibit(false)
+			}
+		}));
+		search.add_controller(&keys);
+		search.connect_stop_search(clone!(@weak completions => move |_| completions.popdown()));
+		self_.search_revealer.connect_child_revealed_notify(clone!(@weak completions => move |revealer| {
+			if !revealer.is_child_revealed() {
+				completions.popdown();
+			}
+		}));
+
+		self_.completions.set(completions).unwrap();
+	}
+
+	fn update_completions(&self) {
+		let self_ = imp::UtopiaWindow::from_instance(self);
+		let popover = self_.completions.get().unwrap();
+		let list = match popover.child().and_then(|list| list.downcast::<gtk::ListBox>().ok()) {
+			Some(list) => list,
+			None => return
+		};
+		while let Some(row) = list.row_at_index(0) {
+			list.remove(&row);
+		}
+
+		let store = self_.store.clone();
+		let mut completions = crate::query::complete(&self_.search.text(), |key| match key {
+			crate::query::Key::Provider => store.provider_names(),
+			crate::query::Key::Kind => store.kind_names(),
+			crate::query::Key::Tag => crate::tags::all(),
+			crate::query::Key::Status => Vec::new()
+		});
+		completions.truncate(8);
+		for completion in &completions {
+			let label = gtk::LabelBuilder::new()
+				.label(&completion.label)
+				.xalign(0.0)
+				.margin_top(4)
+				.margin_bottom(4)
+				.margin_start(6)
+				.margin_end(6)
+				.build();
+			list.append(&gtk::ListBoxRowBuilder::new().child(&label).build());
+		}
+
+		if completions.is_empty() {
+			popover.popdown();
+		} else {
+			popover.popup();
+		}
+		self_.completion_items.replace(completions);
+	}
+
 	pub fn setup_sidebar(&self) {
 		let self_ = imp::UtopiaWindow::from_instance(self);
 		//let ibuf =
@@ -205,12 +324,263 @@ impl UtopiaWindow {
 		let r#box = gtk::BoxBuilder::new().orientation(gtk::Orientation::Horizontal).build();
 		r#box.append(&icon);
 		r#box.append(&label);
-		let all = gtk::ListBoxRowBuilder::new()
-			.child(&r#box)
-			.name("dev.sp1rit.Utopia.restricted.µtopia_all")
+		let count = gtk::LabelBuilder::new()
+			.label("0")
+			.css_classes(vec![String::from("dim-label")])
+			.tooltip_text("Games")
+			.hexpand(true)
+			.halign(gtk::Align::End)
+			.margin_end(8)
 			.build();
+		r#box.append(&count);
+		let all = gtk::ListBoxRowBuilder::new().child(&r#box).build();
+		self_.all_list.append(&all);
+		self_
+			.store
+			.model()
+			.connect_items_changed(clone!(@weak count => move |model, _, _, _| {
+				count.set_label(&model.n_items().to_string());
+			}));
+
+		// the integrations in the users order, without the hidden ones
+		let layout = self_.layout.clone();
+		let hidden = gtk::CustomFilter::new(clone!(@strong layout => move |integration| {
+			!layout.is_hidden(&integration.downcast_ref::<UtopiaIntegration>().unwrap().uuid())
+		}));
+		let shown = gtk::FilterListModel::new(Some(self_.store.integrations()), Some(&hidden));
+		let order = gtk::CustomSorter::new(move |a, b| {
+			let position = |integration: &glib::Object| {
+				layout.position(&integration.downcast_ref::<UtopiaIntegration>().unwrap().uuid())
+			};
+			position(a).cmp(&position(b)).into()
+		});
+		let sorted = gtk::SortListModel::new(Some(&shown), Some(&order));
+
+		self_.module.bind_model(
+			Some(&sorted),
+			clone!(@weak self as utopia => @default-panic, move |integration| {
+				let self_ = imp::UtopiaWindow::from_instance(&utopia);
+				let integration = integration.downcast_ref::<UtopiaIntegration>().unwrap();
+				let item = UtopiaIntegrationItem::new();
+				item.bind(integration);
+				item.set_checked(self_.active_integrations.borrow().contains(&integration.uuid()));
+				item.connect_checked(clone!(@weak utopia => move |item, checked| {
+					let self_ = imp::UtopiaWindow::from_instance(&utopia);
+					let uuid = match item.uuid() {
+						Some(uuid) => uuid,
+						None => return
+					};
+					let mut integrations = self_.active_integrations.borrow().clone();
+					if integrations.contains(&uuid) == checked {
+						return;
+					}
+					if checked {
+						integrations.push(uuid);
+					} else {
+						integrations.retain(|other| other != &uuid);
+					}
+					utopia.select_integrations(integrations);
+				}));
+				item.connect_dropped(clone!(@weak utopia => move |target, uuid| {
+					let self_ = imp::UtopiaWindow::from_instance(&utopia);
+					let current = utopia.integration_rows();
+					let from = current.iter().position(|other| other == &uuid);
+					let to = match target.uuid().and_then(|target| current.iter().position(|other| other == &target)) {
+						Some(to) => to,
+						None => return
+					};
+					// rows dragged downwards end up below the one they're
+					// dropped on
+					let before = match from {
+						Some(from) if from == to => return,
+						Some(from) if from < to => current.get(to + 1).cloned(),
+						_ => Some(current[to].clone())
+					};
+					// the rows get rebuilt, which they shouldn't while handling
+					// the drop
+					let layout = self_.layout.clone();
+					glib::idle_add_local_once(move || layout.move_before(&current, &uuid, before.as_deref()));
+				}));
+				item.upcast()
+			})
+		);
 
-		self_.module.append(&all);
+		let rename = gio::SimpleAction::new("rename-integration", Some(glib::VariantTy::STRING));
+		rename.connect_activate(clone!(@weak self as utopia => move |_, uuid| {
+			if let Some(uuid) = uuid.and_then(|uuid| uuid.get::<String>()) {
+				utopia.rename_integration(&uuid);
+			}
+		}));
+		self.add_action(&rename);
+
+		let hide = gio::SimpleAction::new("hide-integration", Some(glib::VariantTy::STRING));
+		hide.connect_activate(clone!(@weak self as utopia => move |_, uuid| {
+			let self_ = imp::UtopiaWindow::from_instance(&utopia);
+			if let Some(uuid) = uuid.and_then(|uuid| uuid.get::<String>()) {
+				let mut integrations = self_.active_integrations.borrow().clone();
+				integrations.retain(|other| other != &uuid);
+				utopia.select_integrations(integrations);
+				self_.layout.set_hidden(&uuid, true);
+			}
+		}));
+		self.add_action(&hide);
+
+		let unhide = gio::SimpleAction::new("unhide-integrations", None);
+		unhide.set_enabled(self_.layout.has_hidden());
+		unhide.connect_activate(clone!(@weak self as utopia => move |_, _| {
+			imp::UtopiaWindow::from_instance(&utopia).layout.unhide_all();
+		}));
+		self.add_action(&unhide);
+
+		self_
+			.layout
+			.connect_changed(clone!(@weak self as utopia, @weak hidden, @weak order, @weak unhide => move || {
+				let self_ = imp::UtopiaWindow::from_instance(&utopia);
+				hidden.changed(gtk::FilterChange::Different);
+				order.changed(gtk::SorterChange::Different);
+				let mut index = 0;
+				while let Some(row) = self_.module.row_at_index(index) {
+					if let Some(item) = row.downcast_ref::<UtopiaIntegrationItem>() {
+						item.refresh();
+					}
+					index += 1;
+				}
+				unhide.set_enabled(self_.layout.has_hidden());
+				// rebuilt rows lost their checks and selection
+				utopia.select_integrations(self_.active_integrations.borrow().clone());
+			}));
+
+		let settings = gio::Settings::new(crate::config::APP_ID);
+		let selected: Vec<String> = settings
+			.strv("selected-integrations")
+			.iter()
+			.map(|uuid| uuid.to_string())
+			.collect();
+		self_.active_integrations.replace(selected);
+		self_.settings.set(settings).expect("Failed setting up UtopiaWindow");
+		self.prune_integrations();
+		// a recount removes and adds separately, check once it is done
+		self_
+			.store
+			.integrations()
+			.connect_items_changed(clone!(@weak self as utopia => move |_, _, _, _| {
+				glib::idle_add_local_once(clone!(@weak utopia => move || utopia.prune_integrations()));
+			}));
+	}
+
+	/// Drops integrations that went away from the shown ones, so the
+	/// library isn't filtered by an integration without a row to uncheck.
+	fn prune_integrations(&self) {
+		let self_ = imp::UtopiaWindow::from_instance(self);
+		// the library isn't there yet, keep the ones of the last session
+		if self_.store.model().n_items() == 0 {
+			return;
+		}
+		let active = self_.active_integrations.borrow().clone();
+		let existing: Vec<String> = active
+			.iter()
+			.filter(|uuid| self_.store.integration(uuid).is_some())
+			.cloned()
+			.collect();
+		if existing.len() != active.len() {
+			self.select_integrations(existing);
+		}
+	}
+
+	/// Uuids of the integrations in the sidebar, as shown.
+	fn integration_rows(&self) -> Vec<String> {
+		let self_ = imp::UtopiaWindow::from_instance(self);
+		let mut uuids = Vec::new();
+		let mut index = 0;
+		while let Some(row) = self_.module.row_at_index(index) {
+			if let Some(uuid) = row.downcast_ref::<UtopiaIntegrationItem>().and_then(UtopiaIntegrationItem::uuid) {
+				uuids.push(uuid);
+			}
+			index += 1;
+		}
+		uuids
+	}
+
+	/// Lets the user name the integration, an empty name restores the one
+	/// it reports.
+	fn rename_integration(&self, uuid: &str) {
+		let self_ = imp::UtopiaWindow::from_instance(self);
+		let name = match self_.store.integration(uuid) {
+			Some(integration) => integration.info().name.clone(),
+			None => return
+		};
+		let diag = gtk::Dialog::with_buttons(
+			Some("Rename Integration"),
+			Some(self),
+			gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::USE_HEADER_BAR,
+			&[("_Cancel", gtk::ResponseType::Cancel), ("_Rename", gtk::ResponseType::Accept)]
+		);
+		diag.set_default_response(gtk::ResponseType::Accept);
+
+		let entry = gtk::EntryBuilder::new()
+			.text(&self_.layout.label(uuid, &name))
+			.placeholder_text(&name)
+			.activates_default(true)
+			.margin_top(12)
+			.margin_bottom(12)
+			.margin_start(12)
+			.margin_end(12)
+			.build();
+		diag.content_area().append(&entry);
+
+		let layout = self_.layout.clone();
+		let uuid = uuid.to_owned();
+		diag.connect_response(clone!(@weak entry => move |diag, response| {
+			if response == gtk::ResponseType::Accept {
+				layout.set_label(&uuid, &entry.text());
+			}
+			diag.close();
+		}));
+		diag.show();
+	}
+
+	/// Shows the games of the integrations, or of all if there are none,
+	/// and remembers them for the next start.
+	fn select_integrations(&self, integrations: Vec<String>) {
+		let self_ = imp::UtopiaWindow::from_instance(self);
+		self_.active_integrations.replace(integrations.clone());
+
+		let mut selected_row = None;
+		let mut index = 0;
+		while let Some(row) = self_.module.row_at_index(index) {
+			if let Some(item) = row.downcast_ref::<UtopiaIntegrationItem>() {
+				let uuid = item.uuid();
+				item.set_checked(uuid.as_ref().map_or(false, |uuid| integrations.contains(uuid)));
+				if integrations.len() == 1 && uuid.as_ref() == integrations.first() {
+					selected_row = Some(row.clone());
+				}
+			}
+			index += 1;
+		}
+		// a single integration is shown as selected row, several only by
+		// their checkboxes
+		self_.syncing_selection.set(true);
+		match selected_row {
+			Some(row) => self_.module.select_row(Some(&row)),
+			None => self_.module.unselect_all()
+		}
+		if integrations.is_empty() {
+			self_.all_list.select_row(self_.all_list.row_at_index(0).as_ref());
+		} else {
+			self_.all_list.unselect_all();
+		}
+		self_.syncing_selection.set(false);
+
+		let uuids: Vec<&str> = integrations.iter().map(String::as_str).collect();
+		if let Err(e) = self_
+			.settings
+			.get()
+			.unwrap()
+			.set_strv("selected-integrations", &uuids)
+		{
+			eprintln!("Failed saving the selected integrations: {}", e);
+		}
+		self.update_filter();
 	}
 
 	pub fn setup_library(&self) {
@@ -219,29 +589,33 @@ impl UtopiaWindow {
 		let library: crate::grid::UtopiaGrid = self_.library.get();
 		let detail = self_.detail.get();
 		let leaflet: libadwaita::Leaflet = self_.leaflet.get();
-		let search = self_.search.get();
 		let search_btn = self_.search_btn.get();
-		let active_integration = self_.active_integration.clone();
 		let lfoci = self_.lfoci.clone();
-		self_.module.connect_row_selected(clone!(@weak self as utopia, @weak library, @weak leaflet, @weak search, @weak search_btn, @weak detail => move |_, item| {
-			match item {
-				Some(item) => {
-					let name = item.widget_name();
-					if name == "dev.sp1rit.Utopia.restricted.µtopia_all" {
-						active_integration.replace(None);
-					} else {
-						active_integration.replace(Some(item.widget_name()));
-					}
-					//let map = map.borrow();
-					//let page = map.get(&item.widget_name()).unwrap();
-					//library.set_visible_child(page);
-					utopia.update_filter();
-					search_btn.set_active(false);
-					detail.set_visible(false);
-					leaflet.navigate(NavigationDirection::Forward);
-					lfoci.replace(LeafletFoci::Library);
-				},
-				None => println!("Selection cleared")
+		let show_library = Rc::new(clone!(@weak leaflet, @weak search_btn, @weak detail => move || {
+			search_btn.set_active(false);
+			detail.set_visible(false);
+			leaflet.navigate(NavigationDirection::Forward);
+			lfoci.replace(LeafletFoci::Library);
+		}));
+		self_.module.connect_row_selected(clone!(@weak self as utopia, @strong show_library => move |_, row| {
+			if imp::UtopiaWindow::from_instance(&utopia).syncing_selection.get() {
+				return;
+			}
+			if let Some(uuid) = row
+				.and_then(|row| row.downcast_ref::<UtopiaIntegrationItem>())
+				.and_then(UtopiaIntegrationItem::uuid)
+			{
+				utopia.select_integrations(vec![uuid]);
+				show_library();
+			}
+		}));
+		self_.all_list.connect_row_selected(clone!(@weak self as utopia, @strong show_library => move |_, row| {
+			if imp::UtopiaWindow::from_instance(&utopia).syncing_selection.get() {
+				return;
+			}
+			if row.is_some() {
+				utopia.select_integrations(Vec::new());
+				show_library();
 			}
 		}));
 		let sidebar_header = self_.sidebar_header.get();
@@ -303,36 +677,72 @@ impl UtopiaWindow {
 	pub fn init_listener(&self, sender: futures::channel::mpsc::Sender<crate::uev::UtopiaRequest>) {
 		let self_ = imp::UtopiaWindow::from_instance(self);
 		let (dsender, dreceiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
-		self_.library.init(sender.clone(), dsender);
-		self_.detail.init(sender, dreceiver);
+		self_.library.init(sender.clone(), self_.store.clone(), dsender);
+		self_.detail.init(sender, self_.store.clone(), dreceiver);
+		// the integrations of the last session
+		self.update_filter();
 	}
 
 	pub fn update_filter(&self) {
 		let self_ = imp::UtopiaWindow::from_instance(self);
-		let selected_module = self_.active_integration.borrow();
-		let search = self_.search.text();
-		self_.library.update_filter(selected_module, search);
+		let integrations = self_.active_integrations.borrow().clone();
+		let (query, notices) = crate::query::parse(&self_.search.text());
+		let failed = notices.iter().any(|notice| matches!(notice, crate::query::Notice::Error(_)));
+		for widget in &[self_.search.upcast_ref::<gtk::Widget>(), self_.search_error.upcast_ref()] {
+			if failed {
+				widget.add_css_class("error");
+			} else {
+				widget.remove_css_class("error");
+			}
+		}
+		let messages: Vec<&str> = notices.iter().map(crate::query::Notice::message).collect();
+		self_.search_error.set_label(&messages.join("\n"));
+		self_.search_error.set_visible(!notices.is_empty());
+		self_.library.update_filter(integrations, query);
 	}
 
-	pub fn new_item(&self, item: utopia_common::library::LibraryItemFrontendDetails) {
+	pub fn item_name(&self, uuid: &str) -> Option<String> {
 		let self_ = imp::UtopiaWindow::from_instance(self);
-		let mut integrations = self_.integrations.borrow_mut();
-		let card = crate::grid::card::UtopiaCard::new();
-		card.init(item.clone());
-		self_.library.insert_card(item.uuid, &card);
-		for (uuid, iprov) in &item.providers {
-			if !integrations.contains(uuid) {
-				let item = UtopiaIntegrationItem::new();
-				item.init(&uuid, &iprov.name, iprov.icon.as_deref());
-				item.set_widget_name(&uuid);
-				self_.module.append(&item);
-				integrations.push(uuid.to_owned());
-			}
+		self_.store.item(uuid).map(|item| item.name())
+	}
+
+	pub fn integration_name(&self, uuid: &str) -> Option<String> {
+		let self_ = imp::UtopiaWindow::from_instance(self);
+		self_
+			.store
+			.integration(uuid)
+			.map(|integration| self_.layout.label(uuid, &integration.info().name))
+	}
+
+	pub fn items(&self) -> Vec<(String, String)> {
+		let self_ = imp::UtopiaWindow::from_instance(self);
+		self_.store.items()
+	}
+
+	/// Selects the item in the library and opens its details. Returns
+	/// false if there is no item with that uuid (yet).
+	pub fn show_item(&self, uuid: &str) -> bool {
+		let self_ = imp::UtopiaWindow::from_instance(self);
+		if self_.store.item(uuid).is_none() {
+			return false;
+		}
+		// the item might be hidden by the active integrations, search or
+		// chips
+		if let Some(all) = self_.all_list.row_at_index(0) {
+			self_.all_list.select_row(Some(&all));
 		}
+		self_.search_btn.set_active(false);
+		self_.library.clear_chips();
+		self_.library.select_item(uuid)
+	}
+
+	pub fn set_library(&self, library: Vec<utopia_common::library::LibraryItemFrontendDetails>) {
+		let self_ = imp::UtopiaWindow::from_instance(self);
+		self_.store.replace_all(library);
 	}
 
 	pub fn update_item(&self, item: utopia_common::library::LibraryItemFrontend) {
 		let self_ = imp::UtopiaWindow::from_instance(self);
-		self_.library.update_card(&item.uuid.clone(), item);
+		self_.store.update(item);
 	}
 }